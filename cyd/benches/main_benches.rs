@@ -1,6 +1,7 @@
 mod eval;
 mod search;
+mod smp;
 
 use criterion::criterion_main;
 
-criterion_main!(eval::eval_benches, search::search_benches);
+criterion_main!(eval::eval_benches, search::search_benches, smp::smp_benches);
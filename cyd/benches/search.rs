@@ -1,5 +1,9 @@
 extern crate cyd;
-use cyd::search::{alpha_beta, nega_max, Timer};
+use cyd::evaluate::PawnTable;
+use cyd::search::{
+    alpha_beta, iterative_deepening, nega_max, SearchInfo, ThreadData, Timer, INFINITY,
+};
+use cyd::utils::game::check_if_game_over;
 use cyd::utils::new_tt_table;
 
 use criterion::{criterion_group, Criterion};
@@ -11,7 +15,10 @@ fn nega_max_start_pos(c: &mut Criterion) {
     for depth in 1..4 {
         c.bench_function(
             format!("nega_max depth {} start position", depth).as_str(),
-            |b| b.iter(|| nega_max(board.clone(), depth, Player::White)),
+            |b| {
+                let mut pawn_table = PawnTable::new();
+                b.iter(|| nega_max(board.clone(), depth, Player::White, &mut pawn_table))
+            },
         );
     }
 }
@@ -34,7 +41,7 @@ fn alpha_beta_start_pos(c: &mut Criterion) {
                         &mut tt,
                         true,
                         &None,
-                        &timer
+                        &timer,
                     )
                 })
             },
@@ -60,7 +67,7 @@ fn alpha_beta_queen_take(c: &mut Criterion) {
                         &mut tt,
                         true,
                         &None,
-                        &timer
+                        &timer,
                     )
                 })
             },
@@ -68,8 +75,65 @@ fn alpha_beta_queen_take(c: &mut Criterion) {
     }
 }
 
+const NODE_COUNT_POSITIONS: [(&str, &str); 3] = [
+    (
+        "start position",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    ),
+    (
+        "italian",
+        "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    ),
+    (
+        "middlegame",
+        "r4rk1/ppp1nppp/n3b3/1N1p4/2PP4/1Q1BqN2/PP4PP/R4R1K w - - 2 13",
+    ),
+];
+
+fn count_nodes(board: &Board, depth: u8) -> u64 {
+    let mut tt = new_tt_table();
+    let mut data = ThreadData::new();
+    let timer = Timer::new(10000);
+    iterative_deepening(
+        board.clone(),
+        depth,
+        board.turn(),
+        -INFINITY,
+        INFINITY,
+        &mut tt,
+        true,
+        &None,
+        &timer,
+        &mut data,
+        1,
+        &|_: &SearchInfo| {},
+    );
+    timer.nodes()
+}
+
+//Prints the nodes needed to reach each depth, fewer nodes for the same depth is the
+//gain of a search improvement such as PVS and aspiration windows.
+fn alpha_beta_node_counts(c: &mut Criterion) {
+    for (name, fen) in NODE_COUNT_POSITIONS.iter() {
+        let board = Board::from_fen(fen).unwrap();
+        for depth in 3..6 {
+            println!(
+                "nodes {} depth {}: {}",
+                name,
+                depth,
+                count_nodes(&board, depth)
+            );
+        }
+
+        c.bench_function(
+            format!("iterative deepening depth 5 {}", name).as_str(),
+            |b| b.iter(|| count_nodes(&board, 5)),
+        );
+    }
+}
+
 fn play_game(mut board: Board, depth: u8) {
-    while !board.checkmate() && board.rule_50() != 50 && !board.stalemate() && board.is_ok_quick() {
+    while !check_if_game_over(&board) {
         let mut tt = new_tt_table();
         let timer = Timer::new(10000);
         let (mv, _score) = alpha_beta(
@@ -81,7 +145,7 @@ fn play_game(mut board: Board, depth: u8) {
             &mut tt,
             true,
             &None,
-            &timer
+            &timer,
         );
         board.apply_move(mv);
     }
@@ -101,6 +165,7 @@ criterion_group!(
     search_benches,
     alpha_beta_start_pos,
     alpha_beta_queen_take,
+    alpha_beta_node_counts,
     play_through_game,
     nega_max_start_pos,
 );
@@ -0,0 +1,35 @@
+extern crate cyd;
+use cyd::search::{Engine, InfoCallback, SearchInfo, Timer};
+
+use criterion::{criterion_group, BatchSize, Criterion};
+use pleco::Board;
+use std::sync::Arc;
+
+const SMP_DEPTH: u8 = 6;
+
+//Time to reach a fixed depth with an increasing number of threads, the ratio
+//between the one thread run and the others is the Lazy SMP speedup.
+fn lazy_smp_time_to_depth(c: &mut Criterion) {
+    let board =
+        Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+            .unwrap();
+    let noop: InfoCallback = Arc::new(|_: &SearchInfo| {});
+
+    let mut group = c.benchmark_group("lazy smp");
+    group.sample_size(10);
+    for threads in [1, 2, 4].iter() {
+        group.bench_function(
+            format!("depth {} threads {}", SMP_DEPTH, threads).as_str(),
+            |b| {
+                b.iter_batched(
+                    || Engine::new(*threads, 16),
+                    |mut engine| engine.search(&board, SMP_DEPTH, Timer::infinite(), noop.clone()),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(smp_benches, lazy_smp_time_to_depth);
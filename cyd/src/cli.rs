@@ -9,13 +9,21 @@ pub struct Config {
     pub fen: String,
 
     /// Depth to search
-    #[structopt(short, long, default_value = "5")]
+    #[structopt(short, long, default_value = "6")]
     pub depth: u8,
 
     /// Number of threads to use
     #[structopt(short, long, default_value = "1")]
     pub num_threads: u8,
 
+    /// Size of the transposition table in MB
+    #[structopt(long, default_value = "16")]
+    pub hash: usize,
+
+    /// Number of best lines to search and print
+    #[structopt(long, default_value = "1")]
+    pub multi_pv: u8,
+
     ///Moves, comma seperated
     #[structopt(short, long, default_value = "")]
     pub moves: String,
@@ -25,6 +33,18 @@ pub struct Config {
 
     #[structopt(long)]
     pub alive: bool,
+
+    /// Think on the opponent's time in the --alive protocol
+    #[structopt(long)]
+    pub ponder: bool,
+
+    /// Speak the UCI protocol on stdin/stdout
+    #[structopt(long)]
+    pub uci: bool,
+
+    /// Speak the xboard/CECP protocol on stdin/stdout
+    #[structopt(long)]
+    pub xboard: bool,
 }
 
 pub fn get_config() -> Config {
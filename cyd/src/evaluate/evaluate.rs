@@ -1,19 +1,58 @@
-use pleco::helper::Helper;
-use pleco::{Board, PieceType, Player};
-
+use crate::evaluate::king_safety::king_safety;
+use crate::evaluate::pawns::{pawn_structure, PawnTable};
+use crate::evaluate::pieces::piece_activity;
+use crate::evaluate::tapered::{game_phase, Tapered};
+use crate::search::MATE;
+use pleco::core::score::{Value, PAWN_EG, PAWN_MG};
+use pleco::helper::prelude::piece_value;
+use pleco::{Board, Piece, PieceType, Player};
+
+/// Weights of the evaluation terms, separate for the middlegame and the endgame.
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct EvalParameters {
-    pub psq: f32,
-    pub pinned: f32,
-    pub king_safety: f32,
+    pub psq: Tapered,
+    pub pinned: Tapered,
+    pub king_safety: Tapered,  //Scales the penalty of the king safety table
+    pub doubled_pawn: Tapered, //Penalties are subtracted, every weight is positive
+    pub isolated_pawn: Tapered,
+    pub backward_pawn: Tapered,
+    pub connected_pawn: Tapered,
+    pub passed_pawn: Tapered, //Bonus on the seventh rank, less further back
+    pub blocked_passed_pawn: Tapered,
+    pub knight_mobility: Tapered, //Per safe square attacked
+    pub bishop_mobility: Tapered,
+    pub rook_mobility: Tapered,
+    pub queen_mobility: Tapered,
+    pub bishop_pair: Tapered,
+    pub rook_open_file: Tapered,
+    pub rook_semi_open_file: Tapered,
+    pub rook_on_seventh: Tapered,
+    pub knight_outpost: Tapered,
+    pub trapped_piece: Tapered,
 }
 
 impl Default for EvalParameters {
     fn default() -> Self {
         Self {
-            psq: 0.5,
-            pinned: 10.,
-            king_safety: 2.,
+            psq: Tapered::new(1., 1.),
+            pinned: Tapered::new(10., 5.),
+            king_safety: Tapered::new(1., 0.2),
+            doubled_pawn: Tapered::new(10., 20.),
+            isolated_pawn: Tapered::new(10., 15.),
+            backward_pawn: Tapered::new(8., 10.),
+            connected_pawn: Tapered::new(8., 10.),
+            passed_pawn: Tapered::new(60., 120.),
+            blocked_passed_pawn: Tapered::new(20., 40.),
+            knight_mobility: Tapered::new(4., 4.),
+            bishop_mobility: Tapered::new(5., 5.),
+            rook_mobility: Tapered::new(2., 4.),
+            queen_mobility: Tapered::new(1., 2.),
+            bishop_pair: Tapered::new(30., 50.),
+            rook_open_file: Tapered::new(25., 15.),
+            rook_semi_open_file: Tapered::new(12., 8.),
+            rook_on_seventh: Tapered::new(20., 30.),
+            knight_outpost: Tapered::new(20., 10.),
+            trapped_piece: Tapered::new(50., 30.),
         }
     }
 }
@@ -30,11 +69,32 @@ pub const fn piece_values(piece: PieceType) -> f32 {
     }
 }
 
-fn count_piece_material(board: &Board, player: Player, piece: PieceType) -> f32 {
-    piece_values(piece) * board.count_piece(player, piece) as f32
+const MATERIAL_PIECES: [PieceType; 5] = [
+    PieceType::P,
+    PieceType::N,
+    PieceType::B,
+    PieceType::R,
+    PieceType::Q,
+];
+
+/// Piece values of the evaluation, the middlegame values are the ones of `piece_values`.
+/// Pawns and rooks gain in the endgame, where pawns promote and rooks get open lines.
+const fn tapered_piece_values(piece: PieceType) -> Tapered {
+    match piece {
+        PieceType::P => Tapered::new(100., 125.),
+        PieceType::N => Tapered::new(280., 270.),
+        PieceType::B => Tapered::new(320., 330.),
+        PieceType::R => Tapered::new(479., 520.),
+        PieceType::Q => Tapered::new(929., 950.),
+        PieceType::K | PieceType::None | PieceType::All => Tapered::new(0., 0.),
+    }
+}
+
+fn count_piece_material(board: &Board, player: Player, piece: PieceType) -> Tapered {
+    tapered_piece_values(piece) * board.count_piece(player, piece) as f32
 }
 
-fn material_count_side(board: &Board, player: Player) -> f32 {
+fn material_count_side(board: &Board, player: Player) -> Tapered {
     let pawns = count_piece_material(board, player, PieceType::P);
     let rook = count_piece_material(board, player, PieceType::R);
     let knight = count_piece_material(board, player, PieceType::N);
@@ -43,14 +103,27 @@ fn material_count_side(board: &Board, player: Player) -> f32 {
     pawns + rook + knight + bishop + queen
 }
 
-fn material_count(board: &Board) -> f32 {
+fn material_count(board: &Board) -> Tapered {
     material_count_side(board, Player::White) - material_count_side(board, Player::Black)
 }
 
-fn piece_square_table(board: &Board) -> f32 {
-    let score = board.psq();
-    let psq = score.centipawns();
-    (psq.0 - psq.1) as f32
+/// The middlegame and endgame piece-square scores of pleco in centipawns, white minus
+/// black. pleco adds its piece values to the squares, those are taken out again as the
+/// material is counted by `material_count`.
+fn piece_square_table(board: &Board) -> Tapered {
+    let psq = board.psq();
+    let (mut mg, mut eg) = (psq.mg(), psq.eg());
+    for piece in MATERIAL_PIECES {
+        let count = board.count_piece(Player::White, piece) as Value
+            - board.count_piece(Player::Black, piece) as Value;
+        let white_piece = Piece::make_lossy(Player::White, piece);
+        mg -= count * piece_value(white_piece, false);
+        eg -= count * piece_value(white_piece, true);
+    }
+    Tapered::new(
+        mg as f32 * 100. / PAWN_MG as f32,
+        eg as f32 * 100. / PAWN_EG as f32,
+    )
 }
 
 fn pinned_pieces(board: &Board) -> f32 {
@@ -59,41 +132,35 @@ fn pinned_pieces(board: &Board) -> f32 {
     bp - wp
 }
 
-fn king_safety(board: &Board, player: Player) -> f32 {
-    let king = board.king_sq(player);
-
-    let helper = Helper::new();
-
-    let around = helper.ring_distance(king, 1);
-    let occupied = board.get_occupied_player(player);
-
-    (around & occupied).count_bits() as f32
-}
-
-fn _eval(board: &Board, params: &EvalParameters) -> i64 {
+fn _eval(board: &Board, params: &EvalParameters, pawn_table: &mut PawnTable) -> i64 {
     if board.checkmate() {
         let turn: f32 = match &board.turn() {
             Player::White => 1.0,
             Player::Black => -1.0,
         };
-        return (-turn * (9999.0 - board.ply() as f32)) as i64;
+        //The distance to the mate is added by the search
+        return (-turn * MATE as f32) as i64;
     }
 
     let material = material_count(board);
-    let psq = params.psq * piece_square_table(board);
+    let psq = piece_square_table(board) * params.psq;
     let pinned = params.pinned * pinned_pieces(board);
 
-    let king_safety_white = king_safety(board, Player::White);
-    let king_safety_black = king_safety(board, Player::Black);
-    let k_safety = params.king_safety * (king_safety_white - king_safety_black);
+    let k_safety = king_safety(board, params);
+
+    let pawns = pawn_structure(board, params, pawn_table);
+    let pieces = piece_activity(board, params);
 
-    (material + psq + pinned + k_safety) as i64
+    let score = material + psq + pinned + k_safety + pawns + pieces;
+    score.interpolate(game_phase(board)) as i64
 }
 
-pub fn eval(board: &Board, params: &Option<EvalParameters>) -> i64 {
+/// Static evaluation in centipawns from the side of white. The pawn structure is cached
+/// in `pawn_table`, the table of the search thread calling it.
+pub fn eval(board: &Board, params: &Option<EvalParameters>, pawn_table: &mut PawnTable) -> i64 {
     match params {
-        Some(p) => _eval(board, p),
-        None => _eval(board, &EvalParameters::default()),
+        Some(p) => _eval(board, p, pawn_table),
+        None => _eval(board, &EvalParameters::default(), pawn_table),
     }
 }
 
@@ -104,43 +171,68 @@ mod eval_test {
     #[test]
     fn material_start_pos() {
         let board = Board::start_pos();
-        assert_eq!(0., material_count(&board));
+        assert_eq!(Tapered::new(0., 0.), material_count(&board));
     }
 
     #[test]
     fn material_single_knight() {
         let board = Board::from_fen("2k5/8/8/8/8/5N2/8/2K5 w - - 0 1").unwrap();
-        assert_eq!(280., material_count(&board))
+        assert_eq!(280., material_count(&board).mg)
     }
 
     #[test]
     fn material_black_rook_up() {
         let board = Board::from_fen("2k4r/8/2n5/8/8/5N2/8/2K5 w - - 0 1").unwrap();
-        assert_eq!(-479., material_count(&board));
+        assert_eq!(-479., material_count(&board).mg);
     }
 
     #[test]
     fn material_white_queen_up() {
         let board = Board::from_fen("2k4r/8/2n5/8/8/1Q3N2/8/2K5 w - - 0 1").unwrap();
-        assert_eq!(450., material_count(&board));
+        assert_eq!(450., material_count(&board).mg);
     }
 
     #[test]
     fn psq_start_pos() {
         let board = Board::start_pos();
-        assert_eq!(0., piece_square_table(&board))
+        assert_eq!(Tapered::new(0., 0.), piece_square_table(&board))
+    }
+
+    #[test]
+    fn endgame_material() {
+        let board = Board::from_fen("2k5/8/8/8/8/8/PP6/2K5 w - - 0 1").unwrap();
+        assert_eq!(Tapered::new(200., 250.), material_count(&board));
+        //Only pawns left, the endgame values apply
+        let params = EvalParameters::default();
+        let psq = piece_square_table(&board).eg * params.psq.eg;
+        let k_safety = king_safety(&board, &params).eg;
+        let mut table = PawnTable::new();
+        let pawns = pawn_structure(&board, &params, &mut table).eg;
+        assert_eq!(
+            (250. + psq + k_safety + pawns) as i64,
+            eval(&board, &None, &mut table)
+        );
+    }
+
+    #[test]
+    fn king_activity_counts_in_endgame() {
+        //The same king and pawn ending, the white king centralized or in the corner
+        let central = Board::from_fen("8/8/4k3/8/3K4/8/4P3/8 w - - 0 1").unwrap();
+        let corner = Board::from_fen("8/8/4k3/8/8/8/4P3/K7 w - - 0 1").unwrap();
+        let mut table = PawnTable::new();
+        assert!(eval(&central, &None, &mut table) > eval(&corner, &None, &mut table));
     }
 
     #[test]
     fn checkmate_white() {
         let board = Board::from_fen("4R2k/6pp/8/2p5/6n1/5B2/4PPPP/2K4R b - - 0 1").unwrap();
-        assert_eq!(9999, eval(&board, &None));
+        assert_eq!(9999, eval(&board, &None, &mut PawnTable::new()));
     }
 
     #[test]
     fn checkmate_black() {
         let board = Board::from_fen("3k4/8/8/8/8/8/P7/K1q5 w - - 0 1").unwrap();
-        assert_eq!(-9999, eval(&board, &None));
+        assert_eq!(-9999, eval(&board, &None, &mut PawnTable::new()));
     }
 
     #[test]
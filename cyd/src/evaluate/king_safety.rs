@@ -0,0 +1,216 @@
+use crate::evaluate::pawns::{file_bb, pawn_attacks, relative_rank};
+use crate::evaluate::pieces::squares;
+use crate::evaluate::tapered::Tapered;
+use crate::evaluate::EvalParameters;
+use pleco::{Board, PieceType, Player, SQ};
+
+const MAX_UNITS: u32 = 99;
+const ATTACKERS: [PieceType; 4] = [PieceType::N, PieceType::B, PieceType::R, PieceType::Q];
+//Units per king zone square attacked and for having a safe check, indexed like `ATTACKERS`
+const ZONE_ATTACK_UNITS: [u32; 4] = [2, 2, 3, 5];
+const SAFE_CHECK_UNITS: [u32; 4] = [4, 3, 4, 6];
+const MIN_ZONE_ATTACKERS: u32 = 2; //A single piece rarely gets far on its own
+const MISSING_SHIELD_UNITS: u32 = 2;
+const OPEN_FILE_UNITS: u32 = 3;
+const SEMI_OPEN_FILE_UNITS: u32 = 2;
+//Units of an enemy pawn on a file next to the king, by its rank seen from the king's side
+const STORM_UNITS: [u32; 8] = [0, 0, 3, 2, 1, 0, 0, 0];
+
+/// Penalty for the attack units on a king. It grows slowly for a few units and steeply
+/// once several attackers work together, until it levels off.
+const SAFETY_TABLE: [u32; MAX_UNITS as usize + 1] = [
+    0, 0, 1, 2, 3, 5, 7, 9, 12, 15, 18, 22, 26, 30, 35, 39, 44, 50, 56, 62, 68, 75, 82, 85, 89, 97,
+    105, 113, 122, 131, 140, 150, 169, 180, 191, 202, 213, 225, 237, 248, 260, 272, 283, 295, 307,
+    319, 330, 342, 354, 366, 377, 389, 401, 412, 424, 436, 448, 459, 471, 483, 494, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+];
+
+/// The attack units on the king of one side by the terms they come from, the total and
+/// the penalty the total is turned into.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct KingSafetyTrace {
+    pub attackers: u32, //Enemy pieces attacking the king zone
+    pub zone_attacks: u32,
+    pub safe_checks: u32,
+    pub shield: u32,
+    pub storm: u32,
+    pub open_files: u32,
+    pub units: u32, //Capped at `MAX_UNITS`
+    pub penalty: f32,
+}
+
+/// The squares around the king and the ones in front of those.
+fn king_zone(board: &Board, player: Player) -> u64 {
+    let king = board.king_sq(player);
+    let ring = board.attacks_from(PieceType::K, king, player).0 | (1u64 << king.0);
+    match player {
+        Player::White => ring | (ring << 8),
+        Player::Black => ring | (ring >> 8),
+    }
+}
+
+fn attacks_by(board: &Board, player: Player, piece: PieceType) -> u64 {
+    squares(board.piece_bb(player, piece).0).fold(0, |attacks, square| {
+        attacks | board.attacks_from(piece, SQ(square as u8), player).0
+    })
+}
+
+/// Units of the enemy pieces attacking the king zone and of the checks they can give on
+/// squares not defended by a pawn or piece. Zone attacks only count with
+/// `MIN_ZONE_ATTACKERS` attackers.
+fn piece_attacks(board: &Board, player: Player, trace: &mut KingSafetyTrace) {
+    let them = player.other_player();
+    let king = board.king_sq(player);
+    let zone = king_zone(board, player);
+
+    let defended = ATTACKERS.iter().fold(
+        pawn_attacks(player, board.piece_bb(player, PieceType::P).0),
+        |defended, piece| defended | attacks_by(board, player, *piece),
+    );
+    let safe = !board.get_occupied_player(them).0 & !defended;
+
+    let bishop_checks = board.attacks_from(PieceType::B, king, player).0;
+    let rook_checks = board.attacks_from(PieceType::R, king, player).0;
+    let checks = [
+        board.attacks_from(PieceType::N, king, player).0,
+        bishop_checks,
+        rook_checks,
+        bishop_checks | rook_checks,
+    ];
+
+    let mut zone_attacks = 0;
+    for (i, piece) in ATTACKERS.iter().enumerate() {
+        let mut attacks = 0;
+        for square in squares(board.piece_bb(them, *piece).0) {
+            let targets = board.attacks_from(*piece, SQ(square as u8), them).0;
+            let in_zone = (targets & zone).count_ones();
+            if in_zone > 0 {
+                trace.attackers += 1;
+                zone_attacks += ZONE_ATTACK_UNITS[i] * in_zone;
+            }
+            attacks |= targets;
+        }
+        if attacks & checks[i] & safe != 0 {
+            trace.safe_checks += SAFE_CHECK_UNITS[i];
+        }
+    }
+    if trace.attackers >= MIN_ZONE_ATTACKERS {
+        trace.zone_attacks = zone_attacks;
+    }
+}
+
+/// Units of the files next to and on the king file: missing shield pawns on the two
+/// ranks in front of the king, enemy pawns storming and files without own pawns.
+fn pawn_cover(board: &Board, player: Player, trace: &mut KingSafetyTrace) {
+    let king = board.king_sq(player).0 as u32;
+    let king_rank = relative_rank(player, king);
+    let own_pawns = board.piece_bb(player, PieceType::P).0;
+    let enemy_pawns = board.piece_bb(player.other_player(), PieceType::P).0;
+
+    let king_file = king % 8;
+    for file in king_file.saturating_sub(1)..=(king_file + 1).min(7) {
+        let own = own_pawns & file_bb(file);
+        let enemy = enemy_pawns & file_bb(file);
+
+        let shielded = squares(own)
+            .any(|square| (king_rank + 1..=king_rank + 2).contains(&relative_rank(player, square)));
+        if !shielded {
+            trace.shield += MISSING_SHIELD_UNITS;
+        }
+        if own == 0 {
+            trace.open_files += if enemy == 0 {
+                OPEN_FILE_UNITS
+            } else {
+                SEMI_OPEN_FILE_UNITS
+            };
+        }
+        trace.storm += squares(enemy)
+            .map(|square| STORM_UNITS[relative_rank(player, square)])
+            .sum::<u32>();
+    }
+}
+
+/// Attack units on the king of `player` and the penalty they add up to.
+pub fn king_safety_trace(board: &Board, player: Player) -> KingSafetyTrace {
+    let mut trace = KingSafetyTrace::default();
+    piece_attacks(board, player, &mut trace);
+    pawn_cover(board, player, &mut trace);
+
+    let units =
+        trace.zone_attacks + trace.safe_checks + trace.shield + trace.storm + trace.open_files;
+    trace.units = units.min(MAX_UNITS);
+    trace.penalty = SAFETY_TABLE[trace.units as usize] as f32;
+    trace
+}
+
+/// King safety score, white minus black.
+pub fn king_safety(board: &Board, params: &EvalParameters) -> Tapered {
+    let white = king_safety_trace(board, Player::White);
+    let black = king_safety_trace(board, Player::Black);
+    params.king_safety * (black.penalty - white.penalty)
+}
+
+#[cfg(test)]
+mod king_safety_test {
+    use super::*;
+
+    fn trace(fen: &str, player: Player) -> KingSafetyTrace {
+        king_safety_trace(&Board::from_fen(fen).unwrap(), player)
+    }
+
+    #[test]
+    fn safe_in_start_position() {
+        let board = Board::start_pos();
+        assert_eq!(
+            king_safety_trace(&board, Player::White),
+            KingSafetyTrace::default()
+        );
+        assert_eq!(
+            king_safety(&board, &EvalParameters::default()),
+            Tapered::default()
+        );
+    }
+
+    #[test]
+    fn attackers_on_the_king_zone() {
+        //Queen and knight both hit f2 and h2, the queen can check on h2
+        let attacked = trace("6k1/8/8/8/6nq/8/5PPP/6K1 w - - 0 1", Player::White);
+        assert_eq!(attacked.attackers, 2);
+        assert_eq!(attacked.zone_attacks, 4 * 5 + 2 * 2);
+        assert_eq!(attacked.safe_checks, 6);
+        assert_eq!(attacked.shield, 0);
+
+        //The queen alone is not counted, only its check
+        let queen = trace("6k1/8/8/8/7q/8/5PPP/6K1 w - - 0 1", Player::White);
+        assert_eq!(queen.attackers, 1);
+        assert_eq!(queen.zone_attacks, 0);
+        assert!(queen.penalty < attacked.penalty);
+    }
+
+    #[test]
+    fn pawn_shield_and_open_files() {
+        let fen = "6k1/5ppp/8/8/8/8/PPP5/6K1 w - - 0 1";
+        let white = trace(fen, Player::White);
+        assert_eq!(white.shield, 3 * MISSING_SHIELD_UNITS);
+        assert_eq!(white.open_files, 3 * SEMI_OPEN_FILE_UNITS);
+        let black = trace(fen, Player::Black);
+        assert_eq!(black.shield, 0);
+        assert_eq!(black.open_files, 0);
+
+        let board = Board::from_fen(fen).unwrap();
+        assert!(king_safety(&board, &EvalParameters::default()).mg < 0.);
+    }
+
+    #[test]
+    fn pawn_storm() {
+        let white = trace("6k1/8/8/8/6p1/7p/5PPP/6K1 w - - 0 1", Player::White);
+        assert_eq!(white.storm, STORM_UNITS[2] + STORM_UNITS[3]);
+    }
+
+    #[test]
+    fn penalty_is_nonlinear() {
+        assert!(SAFETY_TABLE[20] > 2 * SAFETY_TABLE[10]);
+        assert!(SAFETY_TABLE.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+}
@@ -1,5 +1,14 @@
 #[allow(clippy::module_inception)]
 mod evaluate;
+mod king_safety;
+mod pawns;
+mod pieces;
+mod tapered;
 
 pub use evaluate::eval;
+pub use evaluate::piece_values;
 pub use evaluate::EvalParameters;
+pub use king_safety::{king_safety, king_safety_trace, KingSafetyTrace};
+pub use pawns::{pawn_structure, pawn_terms, PawnTable, PawnTerms};
+pub use pieces::piece_activity;
+pub use tapered::{game_phase, Tapered, MAX_PHASE};
@@ -0,0 +1,256 @@
+use crate::evaluate::tapered::Tapered;
+use crate::evaluate::EvalParameters;
+use pleco::{Board, PieceType, Player};
+
+const PAWN_TABLE_SIZE: usize = 1 << 14;
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+const FILE_H: u64 = FILE_A << 7;
+//Share of the passed pawn bonus by the rank of the pawn, seen from its own side
+const PASSED_RANK_SCALE: [f32; 8] = [0., 0.1, 0.15, 0.25, 0.4, 0.65, 1., 0.];
+
+/// Pawn structure terms of one side, these only depend on the pawns of both sides.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PawnTerms {
+    pub doubled: u32,
+    pub isolated: u32,
+    pub backward: u32,
+    pub connected: u32,
+    pub passed: u64, //Bitboard of the passed pawns
+}
+
+/// Cached pawn structure of a position, indexed by side.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+struct PawnEntry {
+    key: u64,
+    terms: [PawnTerms; 2],
+}
+
+/// Pawn structures keyed by the pawn zobrist key. The pawns change rarely in a search,
+/// so most evaluations find their pawn structure here. Every search thread owns one.
+pub struct PawnTable {
+    entries: Vec<PawnEntry>,
+}
+
+impl PawnTable {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![PawnEntry::default(); PAWN_TABLE_SIZE],
+        }
+    }
+
+    fn probe(&mut self, board: &Board) -> [PawnTerms; 2] {
+        let key = board.pawn_key();
+        let entry = &mut self.entries[key as usize % PAWN_TABLE_SIZE];
+        if entry.key != key {
+            *entry = PawnEntry {
+                key,
+                terms: [
+                    pawn_terms(board, Player::White),
+                    pawn_terms(board, Player::Black),
+                ],
+            };
+        }
+        entry.terms
+    }
+}
+
+impl Default for PawnTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) fn file_bb(file: u32) -> u64 {
+    FILE_A << file
+}
+
+pub(crate) fn adjacent_files(file: u32) -> u64 {
+    let left = if file > 0 { file_bb(file - 1) } else { 0 };
+    let right = if file < 7 { file_bb(file + 1) } else { 0 };
+    left | right
+}
+
+/// The ranks in front of `rank`, seen from `player`.
+pub(crate) fn forward_ranks(player: Player, rank: u32) -> u64 {
+    match player {
+        Player::White if rank < 7 => !0 << (8 * (rank + 1)),
+        Player::Black => (1 << (8 * rank)) - 1,
+        _ => 0,
+    }
+}
+
+/// Squares attacked by the pawns in `pawns` of `player`.
+pub(crate) fn pawn_attacks(player: Player, pawns: u64) -> u64 {
+    match player {
+        Player::White => ((pawns & !FILE_A) << 7) | ((pawns & !FILE_H) << 9),
+        Player::Black => ((pawns & !FILE_A) >> 9) | ((pawns & !FILE_H) >> 7),
+    }
+}
+
+pub(crate) fn relative_rank(player: Player, square: u32) -> usize {
+    match player {
+        Player::White => (square / 8) as usize,
+        Player::Black => 7 - (square / 8) as usize,
+    }
+}
+
+fn stop_square(player: Player, square: u32) -> u32 {
+    match player {
+        Player::White => square + 8,
+        Player::Black => square - 8,
+    }
+}
+
+/// Classifies every pawn of `player`. A pawn is
+/// - doubled if another pawn of its side is in front of it on the same file,
+/// - isolated if there are no pawns of its side on the adjacent files,
+/// - backward if the pawns on the adjacent files are all in front of it and an enemy
+///   pawn controls the square in front of it,
+/// - connected if it is defended by a pawn or has a pawn next to it,
+/// - passed if no enemy pawn is in front of it on its own or an adjacent file, and it is
+///   the front pawn of its file.
+pub fn pawn_terms(board: &Board, player: Player) -> PawnTerms {
+    let own = board.piece_bb(player, PieceType::P).0;
+    let enemy = board.piece_bb(player.other_player(), PieceType::P).0;
+    let enemy_attacks = pawn_attacks(player.other_player(), enemy);
+    let supported = pawn_attacks(player, own);
+
+    let mut terms = PawnTerms::default();
+    let mut pawns = own;
+    while pawns != 0 {
+        let square = pawns.trailing_zeros();
+        pawns &= pawns - 1;
+
+        let (file, rank) = (square % 8, square / 8);
+        let bb = 1u64 << square;
+        let front = forward_ranks(player, rank);
+        let neighbours = own & adjacent_files(file);
+        let doubled = own & file_bb(file) & front != 0;
+
+        if doubled {
+            terms.doubled += 1;
+        }
+        if neighbours == 0 {
+            terms.isolated += 1;
+        } else if neighbours & !front == 0
+            && enemy_attacks & (1 << stop_square(player, square)) != 0
+        {
+            terms.backward += 1;
+        }
+        let phalanx = neighbours & (0xFF << (8 * rank)) != 0;
+        if phalanx || supported & bb != 0 {
+            terms.connected += 1;
+        }
+        let span = (file_bb(file) | adjacent_files(file)) & front;
+        if !doubled && enemy & span == 0 {
+            terms.passed |= bb;
+        }
+    }
+    terms
+}
+
+/// Passed pawns are worth more the further they are advanced, and less when a piece
+/// stands on the square in front of them.
+fn passed_pawns(board: &Board, player: Player, passed: u64, params: &EvalParameters) -> Tapered {
+    let occupied = board.occupied().0;
+    let mut score = Tapered::default();
+    let mut pawns = passed;
+    while pawns != 0 {
+        let square = pawns.trailing_zeros();
+        pawns &= pawns - 1;
+
+        let scale = PASSED_RANK_SCALE[relative_rank(player, square)];
+        score = score + params.passed_pawn * scale;
+        if occupied & (1 << stop_square(player, square)) != 0 {
+            score = score - params.blocked_passed_pawn * scale;
+        }
+    }
+    score
+}
+
+fn side_score(
+    board: &Board,
+    player: Player,
+    terms: &PawnTerms,
+    params: &EvalParameters,
+) -> Tapered {
+    params.connected_pawn * terms.connected as f32
+        - params.doubled_pawn * terms.doubled as f32
+        - params.isolated_pawn * terms.isolated as f32
+        - params.backward_pawn * terms.backward as f32
+        + passed_pawns(board, player, terms.passed, params)
+}
+
+/// Pawn structure score, white minus black. The terms are looked up in `pawn_table`,
+/// only the weights and the blockers are applied per call.
+pub fn pawn_structure(
+    board: &Board,
+    params: &EvalParameters,
+    pawn_table: &mut PawnTable,
+) -> Tapered {
+    let [white, black] = pawn_table.probe(board);
+    side_score(board, Player::White, &white, params)
+        - side_score(board, Player::Black, &black, params)
+}
+
+#[cfg(test)]
+mod pawns_test {
+    use super::*;
+
+    fn terms(fen: &str, player: Player) -> PawnTerms {
+        pawn_terms(&Board::from_fen(fen).unwrap(), player)
+    }
+
+    #[test]
+    fn doubled_and_isolated() {
+        let white = terms("4k3/8/8/8/8/P7/P7/4K3 w - - 0 1", Player::White);
+        assert_eq!(white.doubled, 1);
+        assert_eq!(white.isolated, 2);
+        assert_eq!(white.connected, 0);
+        //Only the front pawn of the file is passed
+        assert_eq!(white.passed, 1 << 16);
+    }
+
+    #[test]
+    fn connected_pawns() {
+        let white = terms("4k3/8/8/8/3PP3/2P5/8/4K3 w - - 0 1", Player::White);
+        assert_eq!(white.connected, 2);
+        assert_eq!(white.isolated, 0);
+        assert_eq!(white.doubled, 0);
+    }
+
+    #[test]
+    fn backward_pawn() {
+        let fen = "4k3/8/8/2p5/4P3/3P4/8/4K3 w - - 0 1";
+        let white = terms(fen, Player::White);
+        assert_eq!(white.backward, 1);
+        assert_eq!(white.connected, 1);
+        //e4 is passed, d3 is stopped by the pawn on c5
+        assert_eq!(white.passed, 1 << 28);
+
+        let black = terms(fen, Player::Black);
+        assert_eq!(black.isolated, 1);
+        assert_eq!(black.passed, 0);
+    }
+
+    #[test]
+    fn passed_pawn_grows_with_rank() {
+        let params = EvalParameters::default();
+        let far = Board::from_fen("4k3/8/8/8/8/P7/8/4K3 w - - 0 1").unwrap();
+        let advanced = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let blocked = Board::from_fen("n3k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut table = PawnTable::new();
+        let mut score = |board: &Board| pawn_structure(board, &params, &mut table).eg;
+        assert!(score(&advanced) > score(&far));
+        assert!(score(&blocked) < score(&advanced));
+    }
+
+    #[test]
+    fn cached_terms_match() {
+        let board = Board::from_fen("4k3/8/8/2p5/4P3/3P4/8/4K3 w - - 0 1").unwrap();
+        let params = EvalParameters::default();
+        let mut table = PawnTable::new();
+        let first = pawn_structure(&board, &params, &mut table);
+        assert_eq!(pawn_structure(&board, &params, &mut table), first);
+    }
+}
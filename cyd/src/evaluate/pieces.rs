@@ -0,0 +1,235 @@
+use crate::evaluate::pawns::{adjacent_files, file_bb, forward_ranks, pawn_attacks, relative_rank};
+use crate::evaluate::tapered::Tapered;
+use crate::evaluate::EvalParameters;
+use pleco::{Board, PieceType, Player, SQ};
+
+const TRAPPED_ROOK_MOBILITY: u32 = 3; //Rooks with more safe squares can get out
+
+/// Squares of the set bits of `bb`, lowest first.
+pub(crate) fn squares(mut bb: u64) -> impl Iterator<Item = u32> {
+    std::iter::from_fn(move || {
+        if bb == 0 {
+            return None;
+        }
+        let square = bb.trailing_zeros();
+        bb &= bb - 1;
+        Some(square)
+    })
+}
+
+fn rank_bb(rank: usize) -> u64 {
+    0xFF << (8 * rank)
+}
+
+/// Squares a piece of `player` can go to without being taken by a pawn.
+fn safe_squares(board: &Board, player: Player) -> u64 {
+    let them = player.other_player();
+    let enemy_pawns = board.piece_bb(them, PieceType::P).0;
+    !board.get_occupied_player(player).0 & !pawn_attacks(them, enemy_pawns)
+}
+
+fn piece_mobility(board: &Board, player: Player, piece: PieceType, square: u32) -> u32 {
+    let attacks = board.attacks_from(piece, SQ(square as u8), player).0;
+    (attacks & safe_squares(board, player)).count_ones()
+}
+
+/// Safe squares attacked by every piece of type `piece`, squares controlled by enemy
+/// pawns do not count.
+pub fn mobility(board: &Board, player: Player, piece: PieceType) -> u32 {
+    squares(board.piece_bb(player, piece).0)
+        .map(|square| piece_mobility(board, player, piece, square))
+        .sum()
+}
+
+/// Rooks on files without pawns and on files with only enemy pawns.
+pub fn rook_files(board: &Board, player: Player) -> (u32, u32) {
+    let own_pawns = board.piece_bb(player, PieceType::P).0;
+    let enemy_pawns = board.piece_bb(player.other_player(), PieceType::P).0;
+    let (mut open, mut semi_open) = (0, 0);
+    for square in squares(board.piece_bb(player, PieceType::R).0) {
+        let file = file_bb(square % 8);
+        if own_pawns & file == 0 {
+            if enemy_pawns & file == 0 {
+                open += 1;
+            } else {
+                semi_open += 1;
+            }
+        }
+    }
+    (open, semi_open)
+}
+
+/// Rooks on the seventh rank, counted when enemy pawns are still there or the enemy
+/// king is cut off on the eighth rank.
+pub fn rooks_on_seventh(board: &Board, player: Player) -> u32 {
+    let them = player.other_player();
+    let seventh = match player {
+        Player::White => rank_bb(6),
+        Player::Black => rank_bb(1),
+    };
+    let eighth = match player {
+        Player::White => rank_bb(7),
+        Player::Black => rank_bb(0),
+    };
+    let enemy_pawns = board.piece_bb(them, PieceType::P).0;
+    let enemy_king = board.piece_bb(them, PieceType::K).0;
+    if enemy_pawns & seventh == 0 && enemy_king & eighth == 0 {
+        return 0;
+    }
+    (board.piece_bb(player, PieceType::R).0 & seventh).count_ones()
+}
+
+/// Knights on the fourth to sixth rank, defended by a pawn and out of reach of the
+/// enemy pawns.
+pub fn knight_outposts(board: &Board, player: Player) -> u32 {
+    let own_pawns = board.piece_bb(player, PieceType::P).0;
+    let enemy_pawns = board.piece_bb(player.other_player(), PieceType::P).0;
+    let defended = pawn_attacks(player, own_pawns);
+    squares(board.piece_bb(player, PieceType::N).0)
+        .filter(|square| {
+            let rank = relative_rank(player, *square);
+            let front = forward_ranks(player, square / 8) & adjacent_files(square % 8);
+            (3..=5).contains(&rank) && defended & (1 << *square) != 0 && enemy_pawns & front == 0
+        })
+        .count() as u32
+}
+
+/// Rooks shut in by their own uncastled king and bishops on a7/h7 (a2/h2 for black)
+/// cut off by an enemy pawn.
+pub fn trapped_pieces(board: &Board, player: Player) -> u32 {
+    let king = board.king_sq(player).0 as u32;
+    let king_file = king % 8;
+    let rooks = squares(board.piece_bb(player, PieceType::R).0)
+        .filter(|square| {
+            let file = square % 8;
+            let shut_in =
+                (king_file < 4 && file < king_file) || (king_file > 4 && file > king_file);
+            relative_rank(player, king) == 0
+                && relative_rank(player, *square) == 0
+                && shut_in
+                && piece_mobility(board, player, PieceType::R, *square) <= TRAPPED_ROOK_MOBILITY
+        })
+        .count() as u32;
+
+    //Bishop square and the square of the pawn trapping it
+    let traps: [(u32, u32); 2] = match player {
+        Player::White => [(48, 41), (55, 46)],
+        Player::Black => [(8, 17), (15, 22)],
+    };
+    let bishops = board.piece_bb(player, PieceType::B).0;
+    let enemy_pawns = board.piece_bb(player.other_player(), PieceType::P).0;
+    let trapped_bishops = traps
+        .iter()
+        .filter(|(bishop, pawn)| bishops & (1 << *bishop) != 0 && enemy_pawns & (1 << *pawn) != 0)
+        .count() as u32;
+
+    rooks + trapped_bishops
+}
+
+fn side_activity(board: &Board, player: Player, params: &EvalParameters) -> Tapered {
+    let (open, semi_open) = rook_files(board, player);
+    let mut score = params.knight_mobility * mobility(board, player, PieceType::N) as f32
+        + params.bishop_mobility * mobility(board, player, PieceType::B) as f32
+        + params.rook_mobility * mobility(board, player, PieceType::R) as f32
+        + params.queen_mobility * mobility(board, player, PieceType::Q) as f32
+        + params.rook_open_file * open as f32
+        + params.rook_semi_open_file * semi_open as f32
+        + params.rook_on_seventh * rooks_on_seventh(board, player) as f32
+        + params.knight_outpost * knight_outposts(board, player) as f32
+        - params.trapped_piece * trapped_pieces(board, player) as f32;
+    if board.count_piece(player, PieceType::B) >= 2 {
+        score = score + params.bishop_pair;
+    }
+    score
+}
+
+/// Mobility and placement of the pieces, white minus black.
+pub fn piece_activity(board: &Board, params: &EvalParameters) -> Tapered {
+    side_activity(board, Player::White, params) - side_activity(board, Player::Black, params)
+}
+
+#[cfg(test)]
+mod pieces_test {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        Board::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn mobility_excludes_pawn_controlled_squares() {
+        let free = board("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1");
+        assert_eq!(mobility(&free, Player::White, PieceType::N), 8);
+        let controlled = board("4k3/3p4/8/8/3N4/8/8/4K3 w - - 0 1");
+        assert_eq!(mobility(&controlled, Player::White, PieceType::N), 6);
+        //Own pieces block the rook
+        let rook = board("4k3/8/8/8/8/8/P7/R3K3 w - - 0 1");
+        assert_eq!(mobility(&rook, Player::White, PieceType::R), 3);
+    }
+
+    #[test]
+    fn bishop_pair() {
+        let position = board("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1");
+        let with_pair = EvalParameters::default();
+        let without_pair = EvalParameters {
+            bishop_pair: Tapered::default(),
+            ..with_pair
+        };
+        let difference =
+            piece_activity(&position, &with_pair) - piece_activity(&position, &without_pair);
+        assert_eq!(difference, with_pair.bishop_pair);
+    }
+
+    #[test]
+    fn rooks_on_open_files() {
+        let position = board("4k3/p7/8/8/8/8/6P1/R3K1R1 w - - 0 1");
+        assert_eq!(rook_files(&position, Player::White), (0, 1));
+        let position = board("4k3/p7/8/8/8/8/8/R3K2R w - - 0 1");
+        assert_eq!(rook_files(&position, Player::White), (1, 1));
+    }
+
+    #[test]
+    fn rook_on_seventh() {
+        let position = board("4k3/R4ppp/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(rooks_on_seventh(&position, Player::White), 1);
+        //Nothing to attack and the king is not cut off
+        let position = board("8/R7/4k3/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(rooks_on_seventh(&position, Player::White), 0);
+        let position = board("4k3/8/8/8/8/8/PPr5/4K3 b - - 0 1");
+        assert_eq!(rooks_on_seventh(&position, Player::Black), 1);
+    }
+
+    #[test]
+    fn knight_outpost() {
+        let position = board("4k3/8/8/3N4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(knight_outposts(&position, Player::White), 1);
+        //The c-pawn can drive the knight away
+        let position = board("4k3/2p5/8/3N4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(knight_outposts(&position, Player::White), 0);
+        //Not defended by a pawn
+        let position = board("4k3/8/8/3N4/8/8/8/4K3 w - - 0 1");
+        assert_eq!(knight_outposts(&position, Player::White), 0);
+    }
+
+    #[test]
+    fn trapped_rook_and_bishop() {
+        let position = board("4k3/8/8/8/8/8/6PP/6KR w - - 0 1");
+        assert_eq!(trapped_pieces(&position, Player::White), 1);
+        let position = board("4k3/8/8/8/8/8/6PP/4K2R w - - 0 1");
+        assert_eq!(trapped_pieces(&position, Player::White), 0);
+
+        let position = board("4k3/B7/1p6/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(trapped_pieces(&position, Player::White), 1);
+        let position = board("4k3/8/8/8/8/1P6/b7/4K3 b - - 0 1");
+        assert_eq!(trapped_pieces(&position, Player::Black), 1);
+    }
+
+    #[test]
+    fn symmetric_start_position() {
+        let params = EvalParameters::default();
+        assert_eq!(
+            piece_activity(&Board::start_pos(), &params),
+            Tapered::default()
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use pleco::{Board, PieceType, Player};
+use std::ops::{Add, Mul, Sub};
+
+/// Phase of the starting position, the phase drops to 0 as the pieces are traded.
+pub const MAX_PHASE: i32 = 24;
+
+/// A value with separate middlegame and endgame parts, blended by the game phase.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Tapered {
+    pub mg: f32,
+    pub eg: f32,
+}
+
+impl Tapered {
+    pub const fn new(mg: f32, eg: f32) -> Self {
+        Self { mg, eg }
+    }
+
+    /// The value in a position with `phase`, from 0 (endgame) to `MAX_PHASE` (middlegame).
+    pub fn interpolate(self, phase: i32) -> f32 {
+        let phase = phase.clamp(0, MAX_PHASE) as f32;
+        (self.mg * phase + self.eg * (MAX_PHASE as f32 - phase)) / MAX_PHASE as f32
+    }
+}
+
+impl Add for Tapered {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl Sub for Tapered {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Mul<f32> for Tapered {
+    type Output = Self;
+
+    fn mul(self, factor: f32) -> Self {
+        Self::new(self.mg * factor, self.eg * factor)
+    }
+}
+
+/// Multiplies the parts separately, a term by its weight.
+impl Mul for Tapered {
+    type Output = Self;
+
+    fn mul(self, weight: Self) -> Self {
+        Self::new(self.mg * weight.mg, self.eg * weight.eg)
+    }
+}
+
+const fn phase_weight(piece: PieceType) -> i32 {
+    match piece {
+        PieceType::N | PieceType::B => 1,
+        PieceType::R => 2,
+        PieceType::Q => 4,
+        _ => 0,
+    }
+}
+
+/// Game phase from the pieces left on the board, pawns and kings do not count.
+/// Promotions can push it above `MAX_PHASE`, it is capped there.
+pub fn game_phase(board: &Board) -> i32 {
+    let phase: i32 = [PieceType::N, PieceType::B, PieceType::R, PieceType::Q]
+        .iter()
+        .map(|piece| {
+            let count =
+                board.count_piece(Player::White, *piece) + board.count_piece(Player::Black, *piece);
+            phase_weight(*piece) * count as i32
+        })
+        .sum();
+    phase.min(MAX_PHASE)
+}
+
+#[cfg(test)]
+mod tapered_test {
+    use super::*;
+
+    #[test]
+    fn interpolates_by_phase() {
+        let value = Tapered::new(100., 20.);
+        assert_eq!(value.interpolate(MAX_PHASE), 100.);
+        assert_eq!(value.interpolate(0), 20.);
+        assert_eq!(value.interpolate(MAX_PHASE / 2), 60.);
+        assert_eq!(value.interpolate(MAX_PHASE + 4), 100.);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Tapered::new(10., 4.);
+        let b = Tapered::new(2., 1.);
+        assert_eq!(a + b, Tapered::new(12., 5.));
+        assert_eq!(a - b, Tapered::new(8., 3.));
+        assert_eq!(a * 0.5, Tapered::new(5., 2.));
+        assert_eq!(a * b, Tapered::new(20., 4.));
+    }
+
+    #[test]
+    fn phase_from_material() {
+        assert_eq!(game_phase(&Board::start_pos()), MAX_PHASE);
+        let rook_ending = Board::from_fen("8/5k2/8/3p4/8/2R5/4K3/8 w - - 0 1").unwrap();
+        assert_eq!(game_phase(&rook_ending), 2);
+        let pawn_ending = Board::from_fen("8/5k2/8/3p4/8/8/4K3/8 w - - 0 1").unwrap();
+        assert_eq!(game_phase(&pawn_ending), 0);
+    }
+}
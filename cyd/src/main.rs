@@ -1,20 +1,48 @@
 mod cli;
-mod evaluate;
-mod search;
-mod utils;
 
 extern crate clap;
 
+use cyd::{search, utils};
+
+fn print_lines(lines: &[search::RootMove]) {
+    for line in lines {
+        println!("{}, {}", line.mv, search::Score::from_value(line.score));
+    }
+}
+
 fn main() {
     let config = cli::get_config();
-    if config.debug {
-        utils::game::from_start(config.depth, config.num_threads);
-    } else if !config.fen.is_empty() {
-        utils::game::find_move_fen(config.fen, config.depth, config.num_threads);
+    if config.uci {
+        utils::uci::uci_loop(config.depth, config.num_threads, config.hash);
+    } else if config.xboard {
+        utils::xboard::xboard_loop(config.depth, config.num_threads, config.hash);
+    } else if config.debug {
+        utils::game::from_start(config.depth, config.num_threads, config.hash);
     } else if config.alive {
-        utils::game::keep_alive(config.moves, config.depth, config.num_threads);
+        utils::game::keep_alive(
+            config.moves,
+            config.depth,
+            config.num_threads,
+            config.hash,
+            config.ponder,
+        );
+    } else if !config.fen.is_empty() {
+        let lines = utils::game::find_move_fen(
+            config.fen,
+            config.depth,
+            config.num_threads,
+            config.multi_pv,
+            config.hash,
+        );
+        print_lines(&lines);
     } else {
-        let (mv, score) = utils::game::find_move(config.moves, config.depth, config.num_threads);
-        println!("{}, {}", mv, score);
+        let lines = utils::game::find_move(
+            config.moves,
+            config.depth,
+            config.num_threads,
+            config.multi_pv,
+            config.hash,
+        );
+        print_lines(&lines);
     }
 }
@@ -0,0 +1,197 @@
+use pleco::{Board, PieceType, Player};
+
+const FIFTY_MOVE_PLIES: i16 = 100;
+const DARK_SQUARES: u64 = 0xAA55_AA55_AA55_AA55;
+
+/// Keys of the positions played before `board` that can still be repeated, oldest first.
+/// Positions from before the last capture or pawn move can never occur again. Only the
+/// moves since the last shallow clone of the board can be taken back, pass boards on
+/// with `parallel_clone` to keep them.
+pub fn game_history(board: &Board) -> Vec<u64> {
+    let mut b = board.parallel_clone();
+    let mut keys = Vec::new();
+    for _ in 0..board.rule_50().max(0) {
+        if b.depth() == 0 || b.last_move().is_none() {
+            break;
+        }
+        b.undo_move();
+        keys.push(b.zobrist());
+    }
+    keys.reverse();
+    keys
+}
+
+/// Whether the position with `key` repeats one of `history`, the keys of the positions
+/// before it. A single repetition of a position inside the search (the last `ply` keys)
+/// is enough to score it as a draw, positions from the game must already have been
+/// repeated to make it a threefold repetition.
+pub fn is_repetition(key: u64, rule_50: i16, history: &[u64], ply: usize) -> bool {
+    let reversible = (rule_50.max(0) as usize).min(history.len());
+    let mut repetitions = 0;
+
+    //Only positions with the same side to move, every second ply back, can be equal
+    for distance in (2..=reversible).step_by(2) {
+        if history[history.len() - distance] == key {
+            if distance <= ply {
+                return true;
+            }
+            repetitions += 1;
+            if repetitions >= 2 {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn minors(board: &Board, player: Player) -> u8 {
+    board.count_piece(player, PieceType::N) + board.count_piece(player, PieceType::B)
+}
+
+/// Whether pawns, rooks or queens are left on the board.
+fn has_heavy_material(board: &Board) -> bool {
+    [Player::White, Player::Black].iter().any(|player| {
+        board.count_piece(*player, PieceType::P)
+            + board.count_piece(*player, PieceType::R)
+            + board.count_piece(*player, PieceType::Q)
+            > 0
+    })
+}
+
+/// No side has enough material to force a mate: no pawns, rooks or queens and at most
+/// one minor piece each. Lone minor against minor endings can still be lost with help
+/// from the other side, but they are close enough to a draw for the search to score
+/// them as one. Use `dead_position` to end a game.
+pub fn insufficient_material(board: &Board) -> bool {
+    !has_heavy_material(board)
+        && minors(board, Player::White) <= 1
+        && minors(board, Player::Black) <= 1
+}
+
+/// No sequence of legal moves can end in a mate: bare kings, a single minor piece, or
+/// only bishops that all stand on squares of the same colour.
+pub fn dead_position(board: &Board) -> bool {
+    if has_heavy_material(board) {
+        return false;
+    }
+    if minors(board, Player::White) + minors(board, Player::Black) <= 1 {
+        return true;
+    }
+    let knights = board.count_piece(Player::White, PieceType::N)
+        + board.count_piece(Player::Black, PieceType::N);
+    let bishops = board.piece_bb(Player::White, PieceType::B).0
+        | board.piece_bb(Player::Black, PieceType::B).0;
+    knights == 0 && (bishops & DARK_SQUARES == 0 || bishops & !DARK_SQUARES == 0)
+}
+
+/// Draw by the fifty-move rule, a mate on the hundredth ply still counts.
+pub fn fifty_move_draw(board: &Board) -> bool {
+    board.rule_50() >= FIFTY_MOVE_PLIES && !board.checkmate()
+}
+
+/// Draw by repetition, fifty-move rule or insufficient material for a position `ply`
+/// plies into the search, `history` holding the keys of every position before it.
+pub fn is_draw(board: &Board, history: &[u64], ply: usize) -> bool {
+    fifty_move_draw(board)
+        || insufficient_material(board)
+        || is_repetition(board.zobrist(), board.rule_50(), history, ply)
+}
+
+/// Whether the game is drawn by threefold repetition.
+pub fn threefold_repetition(board: &Board) -> bool {
+    is_repetition(board.zobrist(), board.rule_50(), &game_history(board), 0)
+}
+
+#[cfg(test)]
+mod draw_test {
+    use super::*;
+
+    fn play(moves: &str) -> Board {
+        let mut board = Board::start_pos();
+        for mv in moves.split(' ') {
+            assert!(board.apply_uci_move(mv));
+        }
+        board
+    }
+
+    #[test]
+    fn history_stops_at_irreversible_move() {
+        let board = play("e2e4 e7e5 g1f3 b8c6");
+        let history = game_history(&board);
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn threefold_repetition_in_game() {
+        let twice = play("g1f3 g8f6 f3g1 f6g8");
+        assert!(!threefold_repetition(&twice));
+
+        let three_times = play("g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8");
+        assert!(threefold_repetition(&three_times));
+    }
+
+    #[test]
+    fn single_repetition_inside_search() {
+        let board = play("g1f3 g8f6 f3g1 f6g8");
+        let history = game_history(&board);
+        assert!(is_repetition(board.zobrist(), board.rule_50(), &history, 4));
+        assert!(!is_repetition(
+            board.zobrist(),
+            board.rule_50(),
+            &history,
+            2
+        ));
+    }
+
+    #[test]
+    fn fifty_move_rule() {
+        let board = Board::from_fen("8/8/4k3/8/8/3K4/4R3/8 w - - 100 80").unwrap();
+        assert!(fifty_move_draw(&board));
+        let board = Board::from_fen("8/8/4k3/8/8/3K4/4R3/8 w - - 99 80").unwrap();
+        assert!(!fifty_move_draw(&board));
+    }
+
+    #[test]
+    fn insufficient_material_positions() {
+        let draws = [
+            "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/4N3/8 w - - 0 1",
+            "8/8/4kb2/8/8/3K4/4B3/8 w - - 0 1",
+        ];
+        for fen in draws.iter() {
+            assert!(insufficient_material(&Board::from_fen(fen).unwrap()));
+        }
+
+        let not_draws = [
+            "8/8/4k3/8/8/3K4/4P3/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/3BB3/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/4R3/8 w - - 0 1",
+        ];
+        for fen in not_draws.iter() {
+            assert!(!insufficient_material(&Board::from_fen(fen).unwrap()));
+        }
+    }
+
+    #[test]
+    fn dead_positions() {
+        let dead = [
+            "8/8/4k3/8/8/3K4/8/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/4N3/8 w - - 0 1",
+            "8/8/4kb2/8/8/3K4/3B4/8 w - - 0 1",
+        ];
+        for fen in dead.iter() {
+            assert!(dead_position(&Board::from_fen(fen).unwrap()));
+        }
+
+        //Mates exist with minor against minor, unless both bishops share a colour
+        let alive = [
+            "8/8/4kb2/8/8/3K4/4B3/8 w - - 0 1",
+            "8/8/4kn2/8/8/3K4/4B3/8 w - - 0 1",
+            "8/8/4kn2/8/8/3K4/4N3/8 w - - 0 1",
+            "8/8/4k3/8/8/3K4/4P3/8 w - - 0 1",
+        ];
+        for fen in alive.iter() {
+            assert!(!dead_position(&Board::from_fen(fen).unwrap()));
+        }
+    }
+}
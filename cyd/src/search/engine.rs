@@ -0,0 +1,230 @@
+use crate::search::transposition_table::TranspositionTable;
+use crate::search::{search_parallel, InfoCallback, RootMove, SearchParameters, ThreadData, Timer};
+use crate::utils::{add_opening_book, tt_table_with_size};
+use pleco::Board;
+
+/// Search state that lives for a whole game.
+///
+/// The transposition table and the per thread data are reused from one move to the
+/// next, so the work of earlier searches speeds up the following ones.
+pub struct Engine {
+    tt_table: TranspositionTable,
+    threads: Vec<ThreadData>,
+    multi_pv: u8,
+    hash: usize, //MB
+    params: SearchParameters,
+    pondered: bool, //The next search continues the generation of the ponder search
+    nodes: u64,     //Nodes of the last search, as counted by its timer
+}
+
+impl Engine {
+    pub fn new(num_threads: u8, hash: usize) -> Self {
+        let mut engine = Self {
+            tt_table: tt_table_with_size(hash),
+            threads: Vec::new(),
+            multi_pv: 1,
+            hash,
+            params: SearchParameters::default(),
+            pondered: false,
+            nodes: 0,
+        };
+        engine.set_num_threads(num_threads);
+        engine
+    }
+
+    pub fn num_threads(&self) -> u8 {
+        self.threads.len() as u8
+    }
+
+    pub fn set_num_threads(&mut self, num_threads: u8) {
+        let params = self.params;
+        self.threads.resize_with(num_threads.max(1) as usize, || {
+            ThreadData::with_params(params)
+        });
+    }
+
+    pub fn search_params(&self) -> SearchParameters {
+        self.params
+    }
+
+    pub fn set_search_params(&mut self, params: SearchParameters) {
+        self.params = params;
+        for data in self.threads.iter_mut() {
+            data.params = params;
+        }
+    }
+
+    pub fn multi_pv(&self) -> u8 {
+        self.multi_pv
+    }
+
+    pub fn set_multi_pv(&mut self, multi_pv: u8) {
+        self.multi_pv = multi_pv.max(1);
+    }
+
+    pub fn hash(&self) -> usize {
+        self.hash
+    }
+
+    /// Replaces the transposition table with an empty one of `hash` MB.
+    pub fn set_hash(&mut self, hash: usize) {
+        self.hash = hash.max(1);
+        self.tt_table = tt_table_with_size(self.hash);
+    }
+
+    /// Nodes searched by all threads in the last search, the count reported to the GUI
+    /// and checked against a node limit.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn tt_table(&self) -> &TranspositionTable {
+        &self.tt_table
+    }
+
+    /// Empties the transposition table, keeping the opening book.
+    pub fn clear_hash(&self) {
+        self.tt_table.clear();
+        add_opening_book(&self.tt_table);
+    }
+
+    /// Forgets everything learned in the previous game.
+    pub fn new_game(&mut self) {
+        self.clear_hash();
+        self.pondered = false;
+        for data in self.threads.iter_mut() {
+            *data = ThreadData::with_params(self.params);
+        }
+    }
+
+    /// Searches the position, entries stored by earlier searches are kept but
+    /// replaced before the ones from this search. A search after `ponder` keeps the
+    /// generation of the ponder search, its entries are as current as its own.
+    pub fn search(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        timer: Timer,
+        report: InfoCallback,
+    ) -> Vec<RootMove> {
+        if !self.pondered {
+            self.tt_table.new_search();
+        }
+        self.pondered = false;
+        self.run(board, depth, timer, report)
+    }
+
+    /// Searches the expected position on the opponent's time, starting the generation
+    /// the next `search` continues.
+    pub fn ponder(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        timer: Timer,
+        report: InfoCallback,
+    ) -> Vec<RootMove> {
+        self.tt_table.new_search();
+        self.pondered = true;
+        self.run(board, depth, timer, report)
+    }
+
+    fn run(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        timer: Timer,
+        report: InfoCallback,
+    ) -> Vec<RootMove> {
+        let counter = timer.clone();
+        let lines = search_parallel(
+            board.parallel_clone(),
+            depth,
+            board.turn(),
+            self.multi_pv,
+            &self.tt_table,
+            &mut self.threads,
+            timer,
+            report,
+        );
+        self.nodes = counter.nodes();
+        lines
+    }
+}
+
+#[cfg(test)]
+mod engine_test {
+    use super::*;
+    use crate::search::{SearchInfo, MAX_DEPTH};
+    use std::sync::Arc;
+
+    #[test]
+    fn keeps_table_between_searches() {
+        let mut engine = Engine::new(2, 1);
+        let board = Board::start_pos();
+        let noop: InfoCallback = Arc::new(|_: &SearchInfo| {});
+
+        let generation = engine.tt_table().generation();
+        let timer = Timer::new(10);
+        engine.search(&board, 3, timer.clone(), noop.clone());
+        assert!(engine.tt_table().get(&board.zobrist()).is_some());
+        assert!(engine.nodes() > 0);
+        assert_eq!(engine.nodes(), timer.nodes());
+
+        engine.search(&board, 3, Timer::new(10), noop);
+        assert_ne!(engine.tt_table().generation(), generation);
+        assert_eq!(engine.num_threads(), 2);
+    }
+
+    #[test]
+    fn search_after_ponder_keeps_generation() {
+        let mut engine = Engine::new(1, 1);
+        let board = Board::start_pos();
+        let noop: InfoCallback = Arc::new(|_: &SearchInfo| {});
+
+        engine.ponder(&board, 2, Timer::new(10), noop.clone());
+        let generation = engine.tt_table().generation();
+        engine.search(&board, 2, Timer::new(10), noop.clone());
+        assert_eq!(engine.tt_table().generation(), generation);
+        engine.search(&board, 2, Timer::new(10), noop);
+        assert_ne!(engine.tt_table().generation(), generation);
+    }
+
+    #[test]
+    fn node_limited_search_is_reproducible() {
+        let board = Board::start_pos();
+        let search = || {
+            let mut engine = Engine::new(1, 1);
+            let timer = Timer::infinite().with_node_limit(20_000);
+            let lines = engine.search(
+                &board,
+                MAX_DEPTH,
+                timer.clone(),
+                Arc::new(|_: &SearchInfo| {}),
+            );
+            assert_eq!(engine.nodes(), timer.nodes());
+            (lines[0].mv.stringify(), lines[0].score, timer.nodes())
+        };
+
+        let (mv, score, nodes) = search();
+        assert!(nodes >= 20_000);
+        assert_eq!(search(), (mv, score, nodes));
+    }
+
+    #[test]
+    fn set_options() {
+        let mut engine = Engine::new(1, 1);
+        engine.set_num_threads(4);
+        engine.set_multi_pv(0);
+        engine.set_hash(2);
+        engine.set_search_params(SearchParameters {
+            lmp_depth: 0,
+            ..SearchParameters::default()
+        });
+        engine.set_num_threads(6);
+        assert_eq!(engine.num_threads(), 6);
+        assert_eq!(engine.search_params().lmp_depth, 0);
+        assert_eq!(engine.multi_pv(), 1);
+        assert_eq!(engine.hash(), 2);
+        assert!(engine.tt_table().capacity() > TranspositionTable::new(1).capacity());
+    }
+}
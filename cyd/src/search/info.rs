@@ -0,0 +1,90 @@
+use crate::search::Score;
+use pleco::BitMove;
+use std::fmt;
+use std::sync::Arc;
+
+/// Progress of the search, reported after every completed iteration.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchInfo {
+    pub depth: u8,
+    pub seldepth: u8,
+    pub multipv: u8,
+    pub score: i64, //Mates are rendered in moves, see `Score`
+    pub nodes: u64,
+    pub time: u64, //milliseconds
+    pub hashfull: u16,
+    pub pv: Vec<BitMove>,
+}
+
+pub type InfoCallback = Arc<dyn Fn(&SearchInfo) + Send + Sync>;
+
+impl SearchInfo {
+    pub fn nps(&self) -> u64 {
+        self.nodes * 1000 / self.time.max(1)
+    }
+
+    pub fn pv_string(&self) -> String {
+        self.pv
+            .iter()
+            .map(|mv| mv.stringify())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+/// Formats the info as an UCI `info` line.
+impl fmt::Display for SearchInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "info depth {} seldepth {} multipv {} score {} nodes {} nps {} time {} hashfull {} pv {}",
+            self.depth,
+            self.seldepth,
+            self.multipv,
+            Score::from_value(self.score),
+            self.nodes,
+            self.nps(),
+            self.time,
+            self.hashfull,
+            self.pv_string()
+        )
+    }
+}
+
+#[cfg(test)]
+mod info_test {
+    use super::*;
+    use crate::utils::find_move;
+    use pleco::Board;
+
+    #[test]
+    fn uci_info_line() {
+        let board = Board::start_pos();
+        let e2e4 = find_move(&board, "e2e4");
+
+        let info = SearchInfo {
+            depth: 3,
+            seldepth: 7,
+            multipv: 1,
+            score: 25,
+            nodes: 3000,
+            time: 1500,
+            hashfull: 12,
+            pv: vec![e2e4],
+        };
+        assert_eq!(
+            info.to_string(),
+            "info depth 3 seldepth 7 multipv 1 score cp 25 nodes 3000 nps 2000 time 1500 hashfull 12 pv e2e4"
+        );
+    }
+
+    #[test]
+    fn uci_info_mate_score() {
+        let info = SearchInfo {
+            depth: 4,
+            score: crate::search::mate_in(3),
+            ..SearchInfo::default()
+        };
+        assert!(info.to_string().contains("score mate 2 "));
+    }
+}
@@ -1,8 +1,27 @@
+mod draw;
+mod engine;
+mod info;
+mod move_picker;
+mod ordering;
+mod parameters;
+mod pv;
+mod score;
 #[allow(clippy::module_inception)]
 mod search;
 mod search_test;
+mod see;
+mod thread_data;
 mod timer;
 pub mod transposition_table;
 
+pub use draw::{dead_position, fifty_move_draw, insufficient_material, threefold_repetition};
+pub use engine::Engine;
+pub use info::{InfoCallback, SearchInfo};
+pub use ordering::{CounterMoves, HistoryTable, KillerMoves, HISTORY_MAX};
+pub use parameters::SearchParameters;
+pub use pv::{PvTable, RootMove, MAX_DEPTH, MAX_PLY};
+pub use score::{is_mate_score, mate_in, mated_in, Score, INFINITY, MATE, MATE_IN_MAX_PLY};
 pub use search::*;
-pub use timer::Timer;
+pub use see::{mvv_lva, see, see_ge};
+pub use thread_data::ThreadData;
+pub use timer::{TimeControl, Timer};
@@ -0,0 +1,424 @@
+use crate::search::ordering::{HistoryTable, KILLER_SLOTS};
+use crate::search::see::{mvv_lva, see_ge};
+use crate::search::{ThreadData, HISTORY_MAX};
+use pleco::core::GenTypes;
+use pleco::{BitMove, Board, Piece};
+
+pub(crate) const MAX_MOVES: usize = 256;
+const PROMOTION_BONUS: u32 = 64; //Above every MVV-LVA score, promotions are tried first
+const EVASION_TT_SCORE: u32 = 200_000;
+const EVASION_CAPTURE_SCORE: u32 = 100_000; //Above every history score
+
+/// Moves of one stage with their ordering score, kept inline so no stage allocates.
+struct ScoredMoves {
+    moves: [(BitMove, u32); MAX_MOVES],
+    len: usize,
+}
+
+impl ScoredMoves {
+    fn new() -> Self {
+        Self {
+            moves: [(BitMove::null(), 0); MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn push(&mut self, mv: BitMove, score: u32) {
+        if self.len < MAX_MOVES {
+            self.moves[self.len] = (mv, score);
+            self.len += 1;
+        }
+    }
+
+    /// Removes and returns the best scoring move. A selection step instead of a sort,
+    /// after a cutoff the remaining moves are never ordered.
+    fn pop_best(&mut self) -> Option<BitMove> {
+        if self.len == 0 {
+            return None;
+        }
+        let mut best = 0;
+        for i in 1..self.len {
+            if self.moves[i].1 > self.moves[best].1 {
+                best = i;
+            }
+        }
+        let (mv, _) = self.moves[best];
+        self.len -= 1;
+        self.moves[best] = self.moves[self.len];
+        Some(mv)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum QuiescenceStage {
+    GenerateEvasions,
+    Evasions,
+    GenerateCaptures,
+    Captures,
+    GenerateQuietChecks,
+    QuietChecks,
+    Done,
+}
+
+/// Moves searched by the quiescence search, generated one stage at a time: every
+/// evasion when in check, otherwise captures and promotions by MVV-LVA followed by the
+/// quiet checks if they are wanted. Quiet moves are never generated outside of check.
+pub struct QuiescencePicker {
+    stage: QuiescenceStage,
+    moves: ScoredMoves,
+    quiet_checks: bool,
+}
+
+fn score_capture(board: &Board, mv: BitMove) -> u32 {
+    let promotion = if mv.is_promo() { PROMOTION_BONUS } else { 0 };
+    let capture = if mv.is_capture() {
+        mvv_lva(board, mv)
+    } else {
+        0
+    };
+    promotion + capture
+}
+
+impl QuiescencePicker {
+    pub fn new(board: &Board, quiet_checks: bool) -> Self {
+        let stage = if board.in_check() {
+            QuiescenceStage::GenerateEvasions
+        } else {
+            QuiescenceStage::GenerateCaptures
+        };
+        Self {
+            stage,
+            moves: ScoredMoves::new(),
+            quiet_checks,
+        }
+    }
+
+    /// The next move to search, `None` once every stage is exhausted.
+    pub fn next(&mut self, board: &Board) -> Option<BitMove> {
+        loop {
+            match self.stage {
+                QuiescenceStage::GenerateEvasions => {
+                    generate_evasions(&mut self.moves, board, BitMove::null(), None);
+                    self.stage = QuiescenceStage::Evasions;
+                }
+                QuiescenceStage::GenerateCaptures => {
+                    self.moves.clear();
+                    for mv in board.generate_moves_of_type(GenTypes::Captures).iter() {
+                        self.moves.push(*mv, score_capture(board, *mv));
+                    }
+                    self.stage = QuiescenceStage::Captures;
+                }
+                QuiescenceStage::GenerateQuietChecks => {
+                    self.moves.clear();
+                    //pleco also returns quiet moves of pieces that can not give check and
+                    //the promotions, which the captures stage already returned
+                    for mv in board.generate_moves_of_type(GenTypes::QuietChecks).iter() {
+                        if !mv.is_promo() && board.gives_check(*mv) {
+                            self.moves.push(*mv, 0);
+                        }
+                    }
+                    self.stage = QuiescenceStage::QuietChecks;
+                }
+                QuiescenceStage::Evasions | QuiescenceStage::QuietChecks => {
+                    if let Some(mv) = self.moves.pop_best() {
+                        return Some(mv);
+                    }
+                    self.stage = QuiescenceStage::Done;
+                }
+                QuiescenceStage::Captures => {
+                    if let Some(mv) = self.moves.pop_best() {
+                        return Some(mv);
+                    }
+                    self.stage = if self.quiet_checks {
+                        QuiescenceStage::GenerateQuietChecks
+                    } else {
+                        QuiescenceStage::Done
+                    };
+                }
+                QuiescenceStage::Done => return None,
+            }
+        }
+    }
+}
+
+/// Quiet moves are ordered by their history, shifted to be positive.
+fn score_quiet(board: &Board, mv: BitMove, history: &HistoryTable) -> u32 {
+    (history.get(board.turn(), mv) + HISTORY_MAX) as u32
+}
+
+/// Every legal move in check, the TT move first, then captures and promotions by
+/// MVV-LVA and the remaining moves by their history when there is one.
+fn generate_evasions(
+    moves: &mut ScoredMoves,
+    board: &Board,
+    tt_move: BitMove,
+    history: Option<&HistoryTable>,
+) {
+    moves.clear();
+    for mv in board.generate_moves().iter() {
+        let score = if *mv == tt_move {
+            EVASION_TT_SCORE
+        } else if mv.is_capture() || mv.is_promo() {
+            EVASION_CAPTURE_SCORE + score_capture(board, *mv)
+        } else {
+            history.map_or(0, |history| score_quiet(board, *mv, history))
+        };
+        moves.push(*mv, score);
+    }
+}
+
+/// Whether `mv`, a move remembered from another position, can be played on `board`.
+fn is_playable(board: &Board, mv: BitMove) -> bool {
+    !mv.is_null() && board.pseudo_legal_move(mv) && board.legal_move(mv)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Stage {
+    TtMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    CounterMove,
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    GenerateEvasions,
+    Evasions,
+    Done,
+}
+
+/// Moves of the main search, yielded one at a time in the order they are most likely to
+/// cause a cutoff: the TT move, captures not losing material by MVV-LVA, the killers, the
+/// countermove, the remaining quiet moves by history and last the losing captures. The
+/// moves of a stage are only generated once the previous stages are exhausted, so a
+/// cutoff by the TT move needs no move generation at all.
+pub struct MovePicker {
+    stage: Stage,
+    tt_move: BitMove,
+    killers: [BitMove; KILLER_SLOTS],
+    killer_index: usize,
+    counter: BitMove,
+    moves: ScoredMoves,
+    bad_captures: ScoredMoves,
+}
+
+impl MovePicker {
+    pub fn new(board: &Board, tt_move: BitMove, data: &ThreadData, ply: u8) -> Self {
+        let stage = if board.in_check() {
+            Stage::GenerateEvasions
+        } else {
+            Stage::TtMove
+        };
+        let counter = data
+            .counter_moves
+            .get(board.last_move().unwrap_or_else(BitMove::null));
+        Self {
+            stage,
+            tt_move,
+            killers: data.killers.get(ply),
+            killer_index: 0,
+            counter,
+            moves: ScoredMoves::new(),
+            bad_captures: ScoredMoves::new(),
+        }
+    }
+
+    /// A killer or countermove is only tried if it is still a quiet move in this position
+    /// and was not tried already as the TT move.
+    fn is_quiet_refutation(&self, board: &Board, mv: BitMove) -> bool {
+        mv != self.tt_move
+            && !mv.is_promo()
+            && board.piece_at_sq(mv.get_dest()) == Piece::None
+            && is_playable(board, mv)
+    }
+
+    /// The next move to search, `None` once every stage is exhausted.
+    pub fn next(&mut self, board: &Board, history: &HistoryTable) -> Option<BitMove> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    if is_playable(board, self.tt_move) {
+                        return Some(self.tt_move);
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    self.moves.clear();
+                    for mv in board.generate_moves_of_type(GenTypes::Captures).iter() {
+                        if *mv != self.tt_move {
+                            self.moves.push(*mv, score_capture(board, *mv));
+                        }
+                    }
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    while let Some(mv) = self.moves.pop_best() {
+                        if see_ge(board, mv, 0) {
+                            return Some(mv);
+                        }
+                        self.bad_captures.push(mv, mvv_lva(board, mv));
+                    }
+                    self.stage = Stage::Killers;
+                }
+                Stage::Killers => {
+                    while self.killer_index < KILLER_SLOTS {
+                        let killer = self.killers[self.killer_index];
+                        self.killer_index += 1;
+                        if self.is_quiet_refutation(board, killer) {
+                            return Some(killer);
+                        }
+                    }
+                    self.stage = Stage::CounterMove;
+                }
+                Stage::CounterMove => {
+                    self.stage = Stage::GenerateQuiets;
+                    if !self.killers.contains(&self.counter)
+                        && self.is_quiet_refutation(board, self.counter)
+                    {
+                        return Some(self.counter);
+                    }
+                }
+                Stage::GenerateQuiets => {
+                    self.moves.clear();
+                    for mv in board.generate_moves_of_type(GenTypes::Quiets).iter() {
+                        let tried =
+                            *mv == self.tt_move || *mv == self.counter || self.killers.contains(mv);
+                        if !tried {
+                            self.moves.push(*mv, score_quiet(board, *mv, history));
+                        }
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    if let Some(mv) = self.moves.pop_best() {
+                        return Some(mv);
+                    }
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => {
+                    if let Some(mv) = self.bad_captures.pop_best() {
+                        return Some(mv);
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::GenerateEvasions => {
+                    generate_evasions(&mut self.moves, board, self.tt_move, Some(history));
+                    self.stage = Stage::Evasions;
+                }
+                Stage::Evasions => {
+                    if let Some(mv) = self.moves.pop_best() {
+                        return Some(mv);
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod move_picker_test {
+    use super::*;
+    use crate::utils::find_move;
+
+    fn picked(fen: &str, quiet_checks: bool) -> Vec<String> {
+        let board = Board::from_fen(fen).unwrap();
+        let mut picker = QuiescencePicker::new(&board, quiet_checks);
+        let mut moves = Vec::new();
+        while let Some(mv) = picker.next(&board) {
+            moves.push(mv.stringify());
+        }
+        moves
+    }
+
+    fn picked_main(board: &Board, tt_move: BitMove, data: &ThreadData, ply: u8) -> Vec<String> {
+        let mut picker = MovePicker::new(board, tt_move, data, ply);
+        let mut moves = Vec::new();
+        while let Some(mv) = picker.next(board, &data.history_table) {
+            moves.push(mv.stringify());
+        }
+        moves
+    }
+
+    fn legal_moves(board: &Board) -> Vec<String> {
+        let mut legal: Vec<String> = board
+            .generate_moves()
+            .iter()
+            .map(|mv| mv.stringify())
+            .collect();
+        legal.sort();
+        legal
+    }
+
+    #[test]
+    fn stages_in_order() {
+        //The pawn takes an undefended knight, the queen takes a defended pawn
+        let board = Board::from_fen("4k3/2p5/3p4/5n2/4P3/8/8/3Q2K1 w - - 0 1").unwrap();
+        let mut data = ThreadData::new();
+        //A killer from another position that can not be played here
+        data.killers
+            .store(3, find_move(&Board::start_pos(), "e2e4"));
+        data.killers.store(3, find_move(&board, "d1d4"));
+        data.history_table
+            .update(board.turn(), find_move(&board, "d1d3"), 500);
+
+        let moves = picked_main(&board, find_move(&board, "g1h1"), &data, 3);
+        assert_eq!(moves[..4], ["g1h1", "e4f5", "d1d4", "d1d3"]);
+        assert_eq!(moves.last().unwrap(), "d1d6");
+
+        let mut sorted = moves.clone();
+        sorted.sort();
+        assert_eq!(sorted, legal_moves(&board));
+    }
+
+    #[test]
+    fn evasions_in_check() {
+        let board = Board::from_fen("4k3/3q4/8/8/8/8/8/4R1K1 b - - 0 1").unwrap();
+        let data = ThreadData::new();
+        let tt_move = find_move(&board, "e8f7");
+        let mut moves = picked_main(&board, tt_move, &data, 0);
+        assert_eq!(moves[0], "e8f7");
+        moves.sort();
+        assert_eq!(moves, legal_moves(&board));
+    }
+
+    #[test]
+    fn captures_by_mvv_lva() {
+        let moves = picked("4k3/8/8/3q4/n1P5/8/8/3QK3 w - - 0 1", false);
+        assert_eq!(moves, vec!["c4d5", "d1d5", "d1a4"]);
+    }
+
+    #[test]
+    fn quiet_checks_after_captures() {
+        let fen = "4k3/8/8/3p4/4P3/8/8/R3K3 w - - 0 1";
+        assert_eq!(picked(fen, false), vec!["e4d5"]);
+
+        let with_checks = picked(fen, true);
+        assert_eq!(with_checks[0], "e4d5");
+        assert!(with_checks.contains(&String::from("a1a8")));
+        assert!(!with_checks.contains(&String::from("a1a7")));
+    }
+
+    #[test]
+    fn checking_promotions_picked_once() {
+        let mut moves = picked("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", true);
+        let count = moves.len();
+        moves.sort();
+        moves.dedup();
+        assert_eq!(moves.len(), count);
+        assert!(moves.contains(&String::from("b7b8q")));
+    }
+
+    #[test]
+    fn every_evasion_in_check() {
+        let fen = "4k3/3q4/8/8/8/8/8/4R1K1 b - - 0 1";
+        let mut moves = picked(fen, false);
+        moves.sort();
+        assert_eq!(moves, legal_moves(&Board::from_fen(fen).unwrap()));
+    }
+}
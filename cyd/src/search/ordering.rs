@@ -0,0 +1,182 @@
+use crate::search::pv::MAX_PLY;
+use pleco::{BitMove, Player};
+
+pub const HISTORY_MAX: i32 = 16_384;
+pub const KILLER_SLOTS: usize = 2;
+
+/// Quiet moves that caused a beta cutoff at the same ply in a sibling node.
+pub struct KillerMoves {
+    moves: [[BitMove; KILLER_SLOTS]; MAX_PLY],
+}
+
+impl KillerMoves {
+    pub fn new() -> Self {
+        Self {
+            moves: [[BitMove::null(); KILLER_SLOTS]; MAX_PLY],
+        }
+    }
+
+    /// Makes `mv` the first killer at `ply`, the previous first killer becomes the second.
+    pub fn store(&mut self, ply: u8, mv: BitMove) {
+        let ply = ply as usize;
+        if ply >= MAX_PLY || self.moves[ply][0] == mv {
+            return;
+        }
+        self.moves[ply][1] = self.moves[ply][0];
+        self.moves[ply][0] = mv;
+    }
+
+    /// The killers stored at `ply`, the most recent first.
+    pub fn get(&self, ply: u8) -> [BitMove; KILLER_SLOTS] {
+        let ply = ply as usize;
+        if ply >= MAX_PLY {
+            return [BitMove::null(); KILLER_SLOTS];
+        }
+        self.moves[ply]
+    }
+}
+
+impl Default for KillerMoves {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn from_to(mv: BitMove) -> (usize, usize) {
+    (mv.get_src().0 as usize, mv.get_dest().0 as usize)
+}
+
+/// Butterfly history, how often a quiet move from one square to another caused a cutoff,
+/// indexed by the side to move.
+pub struct HistoryTable {
+    scores: [[[i32; 64]; 64]; 2],
+}
+
+impl HistoryTable {
+    pub fn new() -> Self {
+        Self {
+            scores: [[[0; 64]; 64]; 2],
+        }
+    }
+
+    pub fn get(&self, player: Player, mv: BitMove) -> i32 {
+        let (from, to) = from_to(mv);
+        self.scores[player as usize][from][to]
+    }
+
+    /// Adds `bonus`, negative for a penalty, scaled down as the score approaches
+    /// `HISTORY_MAX` so the scores stay within +-`HISTORY_MAX`.
+    pub fn update(&mut self, player: Player, mv: BitMove, bonus: i32) {
+        let (from, to) = from_to(mv);
+        let bonus = bonus.clamp(-HISTORY_MAX, HISTORY_MAX);
+        let score = &mut self.scores[player as usize][from][to];
+        *score += bonus - *score * bonus.abs() / HISTORY_MAX;
+    }
+
+    /// Halves every score so the history of earlier searches counts less.
+    pub fn age(&mut self) {
+        for score in self.scores.iter_mut().flatten().flatten() {
+            *score /= 2;
+        }
+    }
+}
+
+impl Default for HistoryTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The quiet move that last refuted a move, indexed by the from and to square of the
+/// refuted move.
+pub struct CounterMoves {
+    moves: [[BitMove; 64]; 64],
+}
+
+impl CounterMoves {
+    pub fn new() -> Self {
+        Self {
+            moves: [[BitMove::null(); 64]; 64],
+        }
+    }
+
+    pub fn get(&self, previous: BitMove) -> BitMove {
+        if previous.is_null() {
+            return BitMove::null();
+        }
+        let (from, to) = from_to(previous);
+        self.moves[from][to]
+    }
+
+    pub fn store(&mut self, previous: BitMove, mv: BitMove) {
+        if previous.is_null() {
+            return;
+        }
+        let (from, to) = from_to(previous);
+        self.moves[from][to] = mv;
+    }
+}
+
+impl Default for CounterMoves {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod ordering_test {
+    use super::*;
+    use crate::utils::find_move;
+    use pleco::Board;
+
+    #[test]
+    fn killers_keep_two_most_recent() {
+        let board = Board::start_pos();
+        let (a, b, c) = (
+            find_move(&board, "e2e4"),
+            find_move(&board, "d2d4"),
+            find_move(&board, "g1f3"),
+        );
+        let mut killers = KillerMoves::new();
+        killers.store(3, a);
+        killers.store(3, a);
+        killers.store(3, b);
+        assert_eq!(killers.get(3), [b, a]);
+
+        killers.store(3, c);
+        assert_eq!(killers.get(3), [c, b]);
+        assert_eq!(killers.get(4), [BitMove::null(); KILLER_SLOTS]);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let board = Board::start_pos();
+        let mv = find_move(&board, "e2e4");
+        let mut history = HistoryTable::new();
+        for _ in 0..1000 {
+            history.update(Player::White, mv, 400);
+        }
+        let score = history.get(Player::White, mv);
+        assert!(score > 0 && score <= HISTORY_MAX);
+        assert_eq!(history.get(Player::Black, mv), 0);
+
+        history.update(Player::White, mv, -HISTORY_MAX);
+        assert!(history.get(Player::White, mv) <= 0);
+
+        history.update(Player::White, mv, 1000);
+        let before = history.get(Player::White, mv);
+        history.age();
+        assert_eq!(history.get(Player::White, mv), before / 2);
+    }
+
+    #[test]
+    fn counter_moves() {
+        let board = Board::start_pos();
+        let (previous, counter) = (find_move(&board, "e2e4"), find_move(&board, "g1f3"));
+        let mut counters = CounterMoves::new();
+        assert!(counters.get(previous).is_null());
+        counters.store(previous, counter);
+        assert_eq!(counters.get(previous), counter);
+        assert!(counters.get(BitMove::null()).is_null());
+    }
+}
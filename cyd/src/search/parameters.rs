@@ -0,0 +1,81 @@
+/// Tunable constants of the selective search. Margins are in centipawns per ply of
+/// remaining depth, depths are the largest remaining depth a pruning is applied at.
+/// The singular extension is applied from `singular_depth` on instead.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SearchParameters {
+    pub null_move_reduction: u8,
+    pub lmr_min_depth: u8,
+    pub lmr_min_moves: u8, //Moves searched at full depth before reducing
+    pub lmr_base: f32,
+    pub lmr_divisor: f32,
+    pub rfp_depth: u8,
+    pub rfp_margin: i64,
+    pub futility_depth: u8,
+    pub futility_margin: i64,
+    pub razor_depth: u8,
+    pub razor_margin: i64,
+    pub lmp_depth: u8,
+    pub lmp_base: u8, //Quiet moves searched at depth 1, grows with the square of the depth
+    pub singular_depth: u8, //Smallest remaining depth the TT move is tested for singularity at
+    pub singular_margin: i64,
+}
+
+impl Default for SearchParameters {
+    fn default() -> Self {
+        Self {
+            null_move_reduction: 2,
+            lmr_min_depth: 3,
+            lmr_min_moves: 3,
+            lmr_base: 0.75,
+            lmr_divisor: 2.25,
+            rfp_depth: 6,
+            rfp_margin: 80,
+            futility_depth: 3,
+            futility_margin: 120,
+            razor_depth: 2,
+            razor_margin: 300,
+            lmp_depth: 4,
+            lmp_base: 3,
+            singular_depth: 6,
+            singular_margin: 2,
+        }
+    }
+}
+
+impl SearchParameters {
+    /// Plies a quiet move is reduced by after `moves` moves were searched at `depth`.
+    pub fn lmr_reduction(&self, depth: u8, moves: usize) -> u8 {
+        if depth < self.lmr_min_depth || moves < self.lmr_min_moves as usize {
+            return 0;
+        }
+        let reduction =
+            self.lmr_base + (depth as f32).ln() * (moves as f32).ln() / self.lmr_divisor;
+        reduction.max(0.) as u8
+    }
+
+    /// Number of quiet moves searched at `depth` before the remaining ones are skipped.
+    pub fn lmp_threshold(&self, depth: u8) -> usize {
+        self.lmp_base as usize + depth as usize * depth as usize
+    }
+}
+
+#[cfg(test)]
+mod parameters_test {
+    use super::*;
+
+    #[test]
+    fn reductions_grow_with_depth_and_moves() {
+        let params = SearchParameters::default();
+        assert_eq!(params.lmr_reduction(2, 20), 0);
+        assert_eq!(params.lmr_reduction(8, 1), 0);
+        assert!(params.lmr_reduction(3, 3) >= 1);
+        assert!(params.lmr_reduction(12, 30) > params.lmr_reduction(4, 5));
+    }
+
+    #[test]
+    fn lmp_threshold_grows_with_depth() {
+        let params = SearchParameters::default();
+        assert_eq!(params.lmp_threshold(1), 4);
+        assert_eq!(params.lmp_threshold(3), 12);
+    }
+}
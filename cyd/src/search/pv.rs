@@ -0,0 +1,90 @@
+use pleco::BitMove;
+
+pub const MAX_PLY: usize = 128;
+/// Deepest iteration searched, leaves room below `MAX_PLY` for extensions.
+pub const MAX_DEPTH: u8 = 100;
+
+/// Triangular table holding the principal variation found below every ply.
+pub struct PvTable {
+    moves: [[BitMove; MAX_PLY]; MAX_PLY],
+    len: [usize; MAX_PLY],
+}
+
+impl PvTable {
+    pub fn new() -> Self {
+        Self {
+            moves: [[BitMove::null(); MAX_PLY]; MAX_PLY],
+            len: [0; MAX_PLY],
+        }
+    }
+
+    pub fn clear(&mut self, ply: u8) {
+        let ply = ply as usize;
+        if ply < MAX_PLY {
+            self.len[ply] = 0;
+        }
+    }
+
+    /// Makes `mv` followed by the line found one ply deeper the pv at `ply`.
+    pub fn update(&mut self, ply: u8, mv: BitMove) {
+        let ply = ply as usize;
+        if ply >= MAX_PLY {
+            return;
+        }
+
+        self.moves[ply][0] = mv;
+        if ply + 1 < MAX_PLY {
+            let child_len = self.len[ply + 1].min(MAX_PLY - ply - 1);
+            let (current, child) = self.moves.split_at_mut(ply + 1);
+            current[ply][1..=child_len].copy_from_slice(&child[0][..child_len]);
+            self.len[ply] = child_len + 1;
+        } else {
+            self.len[ply] = 1;
+        }
+    }
+
+    pub fn line(&self) -> Vec<BitMove> {
+        self.moves[0][..self.len[0]].to_vec()
+    }
+}
+
+/// A searched root move with its score and principal variation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RootMove {
+    pub mv: BitMove,
+    pub score: i64,
+    pub pv: Vec<BitMove>,
+}
+
+impl RootMove {
+    pub fn new(mv: BitMove, score: i64, pv: Vec<BitMove>) -> Self {
+        Self { mv, score, pv }
+    }
+}
+
+impl Default for PvTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod pv_test {
+    use super::*;
+
+    #[test]
+    fn update_copies_child_line() {
+        let mut pv = PvTable::new();
+        let (a, b, c) = (BitMove::new(1), BitMove::new(2), BitMove::new(3));
+
+        pv.clear(2);
+        pv.update(2, c);
+        pv.update(1, b);
+        pv.update(0, a);
+        assert_eq!(pv.line(), vec![a, b, c]);
+
+        pv.clear(1);
+        pv.update(0, b);
+        assert_eq!(pv.line(), vec![b]);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::search::MAX_PLY;
+use std::fmt;
+
+/// Score of being mated in the current position, mates further away score closer to zero.
+pub const MATE: i64 = 9999;
+/// Bound of the search window, no score is outside of it.
+pub const INFINITY: i64 = MATE + 1;
+/// Every score at least this large is a mate found by the search.
+pub const MATE_IN_MAX_PLY: i64 = MATE - MAX_PLY as i64;
+
+/// Score of mating the opponent `ply` plies from the root.
+pub const fn mate_in(ply: u8) -> i64 {
+    MATE - ply as i64
+}
+
+/// Score of being mated `ply` plies from the root.
+pub const fn mated_in(ply: u8) -> i64 {
+    -MATE + ply as i64
+}
+
+pub const fn is_mate_score(score: i64) -> bool {
+    score >= MATE_IN_MAX_PLY || score <= -MATE_IN_MAX_PLY
+}
+
+/// Converts a score relative to the root into one relative to the position at `ply`,
+/// so a stored mate keeps its distance when the position is reached at another ply.
+pub const fn score_to_tt(score: i64, ply: u8) -> i64 {
+    if score >= MATE_IN_MAX_PLY {
+        score + ply as i64
+    } else if score <= -MATE_IN_MAX_PLY {
+        score - ply as i64
+    } else {
+        score
+    }
+}
+
+/// Inverse of `score_to_tt`, converts a stored score back to one relative to the root.
+pub const fn score_from_tt(score: i64, ply: u8) -> i64 {
+    if score >= MATE_IN_MAX_PLY {
+        score - ply as i64
+    } else if score <= -MATE_IN_MAX_PLY {
+        score + ply as i64
+    } else {
+        score
+    }
+}
+
+/// A search score as shown to the user, either centipawns or moves until mate.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Score {
+    Cp(i64),
+    Mate(i64), //Moves, not plies, negative when the side to move is getting mated
+}
+
+impl Score {
+    pub fn from_value(value: i64) -> Self {
+        if value >= MATE_IN_MAX_PLY {
+            Score::Mate((MATE - value + 1) / 2)
+        } else if value <= -MATE_IN_MAX_PLY {
+            Score::Mate(-(MATE + value) / 2)
+        } else {
+            Score::Cp(value)
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Cp(cp) => write!(f, "cp {}", cp),
+            Score::Mate(moves) => write!(f, "mate {}", moves),
+        }
+    }
+}
+
+#[cfg(test)]
+mod score_test {
+    use super::*;
+
+    #[test]
+    fn centipawns() {
+        assert_eq!(Score::from_value(-35), Score::Cp(-35));
+        assert_eq!(Score::from_value(120).to_string(), "cp 120");
+    }
+
+    #[test]
+    fn mate_in_moves() {
+        assert_eq!(Score::from_value(mate_in(1)), Score::Mate(1));
+        assert_eq!(Score::from_value(mate_in(3)), Score::Mate(2));
+        assert_eq!(Score::from_value(mated_in(2)), Score::Mate(-1));
+        assert_eq!(Score::from_value(mated_in(4)).to_string(), "mate -2");
+    }
+
+    #[test]
+    fn tt_conversion_keeps_distance() {
+        //Mate in 5 from the root, found at ply 2 and reached again at ply 4
+        let stored = score_to_tt(mate_in(5), 2);
+        assert_eq!(stored, mate_in(3));
+        assert_eq!(score_from_tt(stored, 4), mate_in(7));
+        assert_eq!(score_from_tt(score_to_tt(mated_in(6), 3), 3), mated_in(6));
+        assert_eq!(score_to_tt(150, 10), 150);
+    }
+}
@@ -1,12 +1,31 @@
-use crate::evaluate::{eval, EvalParameters};
+use crate::evaluate::{eval, EvalParameters, PawnTable};
+use crate::search::draw::{game_history, is_draw};
+use crate::search::move_picker::{MovePicker, QuiescencePicker, MAX_MOVES};
+use crate::search::score::{
+    is_mate_score, mate_in, mated_in, score_from_tt, score_to_tt, INFINITY,
+};
+use crate::search::see::{capture_value, see_ge};
 use crate::search::transposition_table::{EntryFlag, TranspositionTable, TtEntry};
-use crate::search::Timer;
-use crate::utils;
-use pleco::{BitMove, Board, Player};
+use crate::search::{InfoCallback, RootMove, SearchInfo, ThreadData, Timer, MAX_PLY};
+use pleco::{BitMove, Board, PieceType, Player};
+use std::cmp::Reverse;
+use std::collections::HashMap;
 use std::thread;
 
 const DELTA_PRUNING_DIFF: i64 = 200;
-const NULL_MOVE_DEPTH_REDUCTION: u8 = 2;
+const QUIESCENCE_DEPTH: u8 = 10;
+
+//Lazy SMP helper threads skip iterations so that they search different depths than
+//the main thread. Helper i skips depth d when ((d + SKIP_PHASE[i]) / SKIP_SIZE[i]) is odd.
+const SKIP_SIZE: [u8; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [u8; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+const ASPIRATION_WINDOW: i64 = 25; //Half width of the first root window, in centipawns
+const ASPIRATION_MIN_DEPTH: u8 = 4; //Shallower iterations are too unstable for a narrow window
+const VOTE_SCORE_OFFSET: i64 = 14; //Keeps the votes of the worst scoring thread positive
+
+const SEE_PRUNING_DEPTH: u8 = 3; //Losing captures are skipped up to this depth
+const SEE_PRUNING_MARGIN: i64 = 100; //Material a capture may lose per ply of depth
+const MAX_EXTENSIONS: u8 = 16; //Plies one path may be extended by
 
 const fn color_value(player: Player) -> i64 {
     match player {
@@ -15,48 +34,54 @@ const fn color_value(player: Player) -> i64 {
     }
 }
 
-fn score_move(mv: BitMove, board: &Board) -> u32 {
-    if board.gives_check(mv) {
-        20
-    } else if mv.is_capture() {
-        10
-    } else if mv.is_quiet_move() {
-        0
-    } else {
-        5
-    }
+/// Whether a pawn stands on the sixth or seventh rank of its side. The static evaluation
+/// can not tell whether it runs through, so pruning by it is not trusted.
+fn pawn_near_promotion(board: &Board) -> bool {
+    const WHITE_RANKS: u64 = 0x00FF_FF00_0000_0000;
+    const BLACK_RANKS: u64 = 0x0000_0000_00FF_FF00;
+    board.piece_bb(Player::White, PieceType::P).0 & WHITE_RANKS != 0
+        || board.piece_bb(Player::Black, PieceType::P).0 & BLACK_RANKS != 0
 }
 
-fn generate_scored_moves(board: &Board, tt_table: &TranspositionTable) -> Vec<(BitMove, u32)> {
-    let pv_move = tt_table.get(&board.zobrist());
-
-    let mut moves: Vec<(BitMove, u32)> = board
-        .generate_moves()
-        .into_iter()
-        .map(|mv| (mv, score_move(mv, board)))
-        .collect();
-
-    if let Some(tt_entry) = pv_move {
-        moves = moves
-            .into_iter()
-            .map(|mv| {
-                if mv.0 == tt_entry.mv {
-                    (mv.0, 1000)
-                } else {
-                    mv
-                }
-            })
-            .collect();
-    }
+fn is_quiet(mv: BitMove) -> bool {
+    !mv.is_capture() && !mv.is_promo()
+}
 
-    moves.sort_by(|a, b| (b.1).cmp(&a.1));
-    moves
+/// Rewards the quiet move that caused a beta cutoff and penalizes the quiet moves
+/// searched before it.
+fn update_quiet_stats(
+    board: &Board,
+    data: &mut ThreadData,
+    ply: u8,
+    depth: u8,
+    mv: BitMove,
+    searched_quiets: &[BitMove],
+) {
+    let bonus = depth as i32 * depth as i32;
+    let player = board.turn();
+
+    data.killers.store(ply, mv);
+    data.history_table.update(player, mv, bonus);
+    for quiet in searched_quiets {
+        data.history_table.update(player, *quiet, -bonus);
+    }
+    if let Some(previous) = board.last_move() {
+        data.counter_moves.store(previous, mv);
+    }
 }
 
 #[allow(dead_code)] //For benchmarks
-pub fn nega_max(mut board: Board, depth: u8, color: Player) -> (BitMove, i64) {
+pub fn nega_max(
+    mut board: Board,
+    depth: u8,
+    color: Player,
+    pawn_table: &mut PawnTable,
+) -> (BitMove, i64) {
     if depth == 0 {
-        return (BitMove::null(), color_value(color) * eval(&board, &None));
+        return (
+            BitMove::null(),
+            color_value(color) * eval(&board, &None, pawn_table),
+        );
     }
 
     let mut max: i64 = -999999;
@@ -64,7 +89,12 @@ pub fn nega_max(mut board: Board, depth: u8, color: Player) -> (BitMove, i64) {
 
     for mv in board.generate_moves() {
         board.apply_move(mv);
-        let (_, mut score) = nega_max(board.shallow_clone(), depth - 1, color.other_player());
+        let (_, mut score) = nega_max(
+            board.shallow_clone(),
+            depth - 1,
+            color.other_player(),
+            pawn_table,
+        );
         score = -score;
 
         if score > max {
@@ -81,30 +111,52 @@ pub fn nega_max(mut board: Board, depth: u8, color: Player) -> (BitMove, i64) {
 fn quiesce(
     mut board: Board,
     depth: u8,
+    ply: u8,
     color: Player,
     mut alpha: i64,
     beta: i64,
     eval_params: &Option<EvalParameters>,
-    tt_table: &mut TranspositionTable,
     timer: &Timer,
+    data: &mut ThreadData,
 ) -> i64 {
-    let standpat = color_value(color) * eval(&board, eval_params);
+    if timer.poll() {
+        return 0;
+    }
+    data.seldepth = data.seldepth.max(ply);
+
+    //In check there is no standing pat, every evasion is searched
+    let in_check = board.in_check();
+    if in_check && board.checkmate() {
+        return mated_in(ply);
+    }
     if depth == 0 {
-        return standpat;
-    } else if standpat >= beta {
+        return color_value(color) * eval(&board, eval_params, &mut data.pawn_table);
+    }
+    let standpat = if in_check {
+        -INFINITY
+    } else {
+        color_value(color) * eval(&board, eval_params, &mut data.pawn_table)
+    };
+    if standpat >= beta {
         return beta;
     } else if alpha < standpat {
         alpha = standpat;
     }
-
-    if standpat < alpha - DELTA_PRUNING_DIFF {
-        return alpha;
-    }
-    let moves = generate_scored_moves(&board, tt_table);
-
-    for (mv, _) in moves {
-        //Should be possible to only generate capturing moves. Problem with check
-        if !(board.is_capture(mv) || board.gives_check(mv)) {
+    //Quiet checks are only tried at the first ply of the quiescence search
+    let mut picker = QuiescencePicker::new(&board, depth == QUIESCENCE_DEPTH);
+    while let Some(mv) = picker.next(&board) {
+        //A capture losing material can not raise the stand pat score
+        if !in_check && mv.is_capture() && !see_ge(&board, mv, 0) {
+            continue;
+        }
+        //Delta pruning, a capture that can not bring the score close to alpha is skipped
+        //unless it gives check, the check may be worth more than the material
+        if !in_check
+            && mv.is_capture()
+            && !mv.is_promo()
+            && standpat + capture_value(&board, mv) + DELTA_PRUNING_DIFF < alpha
+            && !board.gives_check(mv)
+        {
             continue;
         }
 
@@ -112,15 +164,20 @@ fn quiesce(
         let score = -quiesce(
             board.shallow_clone(),
             depth - 1,
+            ply + 1,
             color.other_player(),
             -beta,
             -alpha,
             eval_params,
-            tt_table,
             timer,
+            data,
         );
         board.undo_move();
 
+        if timer.stopped() {
+            return 0;
+        }
+
         if score >= beta {
             return beta;
         } else if score > alpha {
@@ -134,6 +191,7 @@ fn quiesce(
 pub fn _alpha_beta(
     mut board: Board,
     depth: u8,
+    ply: u8,
     color: Player,
     mut alpha: i64,
     mut beta: i64,
@@ -141,46 +199,151 @@ pub fn _alpha_beta(
     do_null: bool,
     eval_params: &Option<EvalParameters>,
     timer: &Timer,
+    data: &mut ThreadData,
 ) -> (BitMove, i64) {
+    if timer.poll() {
+        return (BitMove::null(), 0);
+    }
+    data.pv.clear(ply);
+    data.seldepth = data.seldepth.max(ply);
+
+    //The root is searched even if it is a draw, there has to be a move to play
+    if ply > 0 && is_draw(&board, &data.history, ply as usize) {
+        return (BitMove::null(), 0);
+    }
+    if ply as usize >= MAX_PLY - 1 {
+        return (
+            BitMove::null(),
+            color_value(color) * eval(&board, eval_params, &mut data.pawn_table),
+        );
+    }
+
+    //Mate distance pruning, no line can be better than mating on the next move
+    if ply > 0 {
+        alpha = alpha.max(mated_in(ply));
+        beta = beta.min(mate_in(ply + 1));
+        if alpha >= beta {
+            return (BitMove::null(), alpha);
+        }
+    }
+
+    //Check extension, a check is searched one ply deeper so that the horizon never
+    //falls in the middle of a forcing sequence. The extensions on one path are limited,
+    //a perpetual check would extend forever.
+    let in_check = board.in_check();
+    let extended = if ply > 0 {
+        data.extensions[ply as usize]
+    } else {
+        0
+    };
+    let check_extension = if in_check && ply > 0 && extended < MAX_EXTENSIONS {
+        1
+    } else {
+        0
+    };
+    let depth = depth + check_extension;
+    let extended = extended + check_extension;
+    data.extensions[ply as usize + 1] = extended;
+
     let zobrist = board.zobrist();
     let alphaorig = alpha;
-
-    if let Some(tt_entry) = tt_table.get(&zobrist) {
-        if tt_entry.depth >= depth {
+    //Move skipped while testing whether the TT move is singular, the result of that
+    //search is not the value of the position
+    let excluded_move = data.excluded_moves[ply as usize];
+    let singular_search = !excluded_move.is_null();
+
+    //No cutoffs at the root, the search has to produce a principal variation
+    let tt_entry = tt_table.get(&zobrist);
+    if let Some(tt_entry) = tt_entry.filter(|_| !singular_search) {
+        if ply > 0 && tt_entry.depth >= depth {
+            let value = score_from_tt(tt_entry.value, ply);
             let flag = &tt_entry.flag;
             if flag == &EntryFlag::Exact {
-                return (tt_entry.mv, tt_entry.value);
+                return (tt_entry.mv, value);
             } else if flag == &EntryFlag::LowerBound {
-                alpha = alpha.max(tt_entry.value);
+                alpha = alpha.max(value);
             } else if flag == &EntryFlag::UpperBound {
-                beta = beta.min(tt_entry.value);
+                beta = beta.min(value);
             }
             if alpha >= beta {
-                return (tt_entry.mv, tt_entry.value);
+                return (tt_entry.mv, value);
             }
         }
     }
 
-    let moves = generate_scored_moves(&board, tt_table);
-    if depth == 0 || board.checkmate() || moves.is_empty() {
+    if depth == 0 {
+        if !in_check && board.stalemate() {
+            return (BitMove::null(), 0);
+        }
         return (
             BitMove::null(),
-            quiesce(board, 10, color, alpha, beta, eval_params, tt_table, timer),
+            quiesce(
+                board,
+                QUIESCENCE_DEPTH,
+                ply,
+                color,
+                alpha,
+                beta,
+                eval_params,
+                timer,
+                data,
+            ),
         );
     }
 
+    let params = data.params;
+    let pv_node = beta - alpha > 1;
+    let static_eval = if in_check {
+        -INFINITY
+    } else {
+        color_value(color) * eval(&board, eval_params, &mut data.pawn_table)
+    };
+
+    if ply > 0 && !pv_node && !in_check && !pawn_near_promotion(&board) {
+        //Reverse futility pruning, the position is so good that the opponent will avoid it
+        if depth <= params.rfp_depth
+            && !is_mate_score(beta)
+            && static_eval - params.rfp_margin * depth as i64 >= beta
+        {
+            return (BitMove::null(), beta);
+        }
+
+        //Razoring, a position this bad is only searched for captures
+        if depth <= params.razor_depth && static_eval + params.razor_margin * (depth as i64) < alpha
+        {
+            let score = quiesce(
+                board.shallow_clone(),
+                QUIESCENCE_DEPTH,
+                ply,
+                color,
+                alpha,
+                beta,
+                eval_params,
+                timer,
+                data,
+            );
+            if score <= alpha {
+                return (BitMove::null(), alpha);
+            }
+        }
+    }
+
     if do_null
-        && !board.in_check()
-        && board.ply() > 0
-        && depth > NULL_MOVE_DEPTH_REDUCTION + 1
-        && depth < 4
+        && !singular_search
+        && !in_check
+        && ply > 0
+        && !pv_node
+        && depth > params.null_move_reduction
+        && static_eval >= beta
         && board.non_pawn_material(color) > 0
     {
+        data.history.push(zobrist);
         unsafe {
             board.apply_null_move();
             let (_, mut score) = _alpha_beta(
                 board.shallow_clone(),
-                depth - 1 - NULL_MOVE_DEPTH_REDUCTION,
+                depth - 1 - params.null_move_reduction,
+                ply + 1,
                 color.other_player(),
                 -beta,
                 -beta + 1,
@@ -188,48 +351,211 @@ pub fn _alpha_beta(
                 false,
                 eval_params,
                 timer,
+                data,
             );
             score = -score;
             board.undo_null_move();
+            data.history.pop();
+            if timer.stopped() {
+                return (BitMove::null(), 0);
+            }
             if score >= beta {
                 return (BitMove::null(), beta);
             }
         }
     }
 
+    //Futility pruning, quiet moves can not bring the score up to alpha
+    let futility_pruning = !pv_node
+        && !in_check
+        && ply > 0
+        && depth <= params.futility_depth
+        && !is_mate_score(alpha)
+        && static_eval + params.futility_margin * (depth as i64) <= alpha;
+
+    //Singular extension, when every other move fails low against a margin below the
+    //value of the TT move, the TT move is the only good one and is searched deeper
+    let mut singular_move = BitMove::null();
+    if let Some(tt_entry) = tt_entry {
+        let tt_value = score_from_tt(tt_entry.value, ply);
+        if ply > 0
+            && !singular_search
+            && depth >= params.singular_depth
+            && !tt_entry.mv.is_null()
+            && tt_entry.flag != EntryFlag::UpperBound
+            && depth <= tt_entry.depth.saturating_add(3)
+            && extended < MAX_EXTENSIONS
+            && !is_mate_score(tt_value)
+        {
+            let singular_beta = tt_value - params.singular_margin * depth as i64;
+            data.excluded_moves[ply as usize] = tt_entry.mv;
+            let (_, score) = _alpha_beta(
+                board.shallow_clone(),
+                (depth - 1) / 2,
+                ply,
+                color,
+                singular_beta - 1,
+                singular_beta,
+                tt_table,
+                false,
+                eval_params,
+                timer,
+                data,
+            );
+            data.excluded_moves[ply as usize] = BitMove::null();
+            if timer.stopped() {
+                return (BitMove::null(), 0);
+            }
+            if score < singular_beta {
+                singular_move = tt_entry.mv;
+            }
+        }
+    }
+
+    let tt_move = tt_entry.map_or_else(BitMove::null, |entry| entry.mv);
+    let mut picker = MovePicker::new(&board, tt_move, data, ply);
     let mut best_move = BitMove::null();
-    for (mv, _) in moves {
-        if timer.elapsed() {
-            break;
+    let mut legal_moves = 0;
+    let mut searched_moves = 0;
+    //Kept inline, no node allocates for the quiet moves it searched
+    let mut searched_quiets = [BitMove::null(); MAX_MOVES];
+    let mut num_quiets = 0;
+    while let Some(mv) = picker.next(&board, &data.history_table) {
+        legal_moves += 1;
+        if ply == 0 && data.excluded_root_moves.contains(&mv) {
+            continue;
+        }
+        if mv == excluded_move {
+            continue;
+        }
+        let extension = if mv == singular_move { 1 } else { 0 };
+        data.extensions[ply as usize + 1] = extended + extension;
+
+        let quiet = is_quiet(mv);
+        let gives_check = board.gives_check(mv);
+        let prunable = ply > 0 && searched_moves > 0 && !in_check && !gives_check;
+
+        //Close to the horizon captures losing a lot of material are not searched
+        if prunable
+            && depth <= SEE_PRUNING_DEPTH
+            && mv.is_capture()
+            && !see_ge(&board, mv, -SEE_PRUNING_MARGIN * depth as i64)
+        {
+            continue;
         }
+
+        if prunable && quiet && !is_mate_score(alpha) {
+            if futility_pruning {
+                continue;
+            }
+            //Late move pruning, with good ordering the last quiet moves are rarely best
+            if !pv_node && depth <= params.lmp_depth && num_quiets >= params.lmp_threshold(depth) {
+                continue;
+            }
+        }
+
+        //Late move reductions, quiet moves ordered late are searched less deep first
+        let mut reduction = 0;
+        if quiet && !in_check && !gives_check {
+            reduction = params.lmr_reduction(depth, searched_moves);
+            if pv_node {
+                reduction = reduction.saturating_sub(1);
+            }
+            reduction = reduction.min(depth.saturating_sub(2));
+        }
+
+        data.history.push(zobrist);
         board.apply_move(mv);
-        let (_, mut score) = _alpha_beta(
-            board.shallow_clone(),
-            depth - 1,
-            color.other_player(),
-            -beta,
-            -alpha,
-            tt_table,
-            true,
-            eval_params,
-            timer,
-        );
+
+        //Principal variation search, the first move is expected to be the best one. The
+        //others only have to be proven worse with a zero window, a move that turns out
+        //better is searched again with the full window.
+        let mut full_window = searched_moves == 0;
+        let mut score = alpha;
+        if !full_window {
+            score = -_alpha_beta(
+                board.shallow_clone(),
+                depth + extension - 1 - reduction,
+                ply + 1,
+                color.other_player(),
+                -alpha - 1,
+                -alpha,
+                tt_table,
+                true,
+                eval_params,
+                timer,
+                data,
+            )
+            .1;
+            //A reduced move that beats alpha is verified at full depth
+            if reduction > 0 && score > alpha && !timer.stopped() {
+                score = -_alpha_beta(
+                    board.shallow_clone(),
+                    depth + extension - 1,
+                    ply + 1,
+                    color.other_player(),
+                    -alpha - 1,
+                    -alpha,
+                    tt_table,
+                    true,
+                    eval_params,
+                    timer,
+                    data,
+                )
+                .1;
+            }
+            full_window = score > alpha && score < beta && !timer.stopped();
+        }
+        if full_window {
+            score = -_alpha_beta(
+                board.shallow_clone(),
+                depth + extension - 1,
+                ply + 1,
+                color.other_player(),
+                -beta,
+                -alpha,
+                tt_table,
+                true,
+                eval_params,
+                timer,
+                data,
+            )
+            .1;
+        }
+        searched_moves += 1;
 
         board.undo_move();
-        score = -score;
+        data.history.pop();
+
+        //The result of an aborted search is discarded, only the root move matters
+        if timer.stopped() {
+            return (best_move, alpha);
+        }
 
+        //A cutoff is stored as a lower bound, zero window re-searches depend on it
         if score >= beta {
-            return (mv, beta);
+            if quiet {
+                update_quiet_stats(&board, data, ply, depth, mv, &searched_quiets[..num_quiets]);
+            }
+            alpha = beta;
+            best_move = mv;
+            break;
         } else if score > alpha {
             alpha = score;
             best_move = mv;
+            data.pv.update(ply, mv);
         }
-
-        if alpha >= beta {
-            break;
+        if quiet {
+            searched_quiets[num_quiets] = mv;
+            num_quiets += 1;
         }
     }
 
+    if legal_moves == 0 {
+        let score = if in_check { mated_in(ply) } else { 0 };
+        return (BitMove::null(), score);
+    }
+
     let value = alpha;
     let mut flag = EntryFlag::Exact;
 
@@ -243,10 +569,14 @@ pub fn _alpha_beta(
         mv: best_move,
         depth,
         flag,
-        value,
+        value: score_to_tt(value, ply),
+        age: tt_table.generation(),
     };
 
-    tt_table.insert(zobrist, entry);
+    //With excluded moves the result is not valid for the position
+    if !singular_search && (ply > 0 || data.excluded_root_moves.is_empty()) {
+        tt_table.insert(zobrist, entry);
+    }
 
     (best_move, alpha)
 }
@@ -263,68 +593,291 @@ pub fn alpha_beta(
     eval_params: &Option<EvalParameters>,
     timer: &Timer,
 ) -> (BitMove, i64) {
-    let mut mv = BitMove::null();
-    let mut latest_score: i64 = 0;
+    let lines = iterative_deepening(
+        board,
+        depth,
+        color,
+        alpha,
+        beta,
+        tt_table,
+        do_null,
+        eval_params,
+        timer,
+        &mut ThreadData::new(),
+        1,
+        &|_: &SearchInfo| {},
+    );
+    (lines[0].mv, lines[0].score)
+}
+
+/// Whether the helper thread `id` should leave iteration `depth` to the other threads.
+fn skip_iteration(id: usize, depth: u8) -> bool {
+    if id == 0 {
+        return false;
+    }
+    let idx = (id - 1) % SKIP_SIZE.len();
+    !((depth + SKIP_PHASE[idx]) / SKIP_SIZE[idx]).is_multiple_of(2)
+}
+
+/// Searches the root with a narrow window around the score of the previous iteration,
+/// widening the side that failed until the score falls inside. No window is wider than
+/// `alpha`..`beta`.
+#[allow(clippy::too_many_arguments)]
+fn aspiration_search(
+    board: &Board,
+    depth: u8,
+    color: Player,
+    alpha: i64,
+    beta: i64,
+    previous_score: Option<i64>,
+    tt_table: &mut TranspositionTable,
+    do_null: bool,
+    eval_params: &Option<EvalParameters>,
+    timer: &Timer,
+    data: &mut ThreadData,
+) -> (BitMove, i64) {
+    let mut delta = ASPIRATION_WINDOW;
+    let (mut low, mut high) = match previous_score {
+        Some(score) if depth >= ASPIRATION_MIN_DEPTH && !is_mate_score(score) => {
+            ((score - delta).max(alpha), (score + delta).min(beta))
+        }
+        _ => (alpha, beta),
+    };
 
-    for d in 1..(depth + 2) {
-        let (m, sc) = _alpha_beta(
+    loop {
+        let (mv, score) = _alpha_beta(
             board.clone(),
-            d,
+            depth,
+            0,
             color,
-            alpha,
-            beta,
+            low,
+            high,
             tt_table,
             do_null,
             eval_params,
             timer,
+            data,
         );
-        if timer.elapsed() {
+        if timer.stopped() {
+            return (mv, score);
+        }
+
+        if score <= low && low > alpha {
+            delta *= 2;
+            low = (score - delta).max(alpha);
+        } else if score >= high && high < beta {
+            delta *= 2;
+            high = (score + delta).min(beta);
+        } else {
+            return (mv, score);
+        }
+    }
+}
+
+/// Searches with increasing depth, calling `report` after every completed line.
+/// Helper threads (`data.id > 0`) skip some of the iterations.
+///
+/// Every iteration searches the `multi_pv` best root moves, each pass excluding the
+/// moves found by the previous ones. Returns the lines of the last completed iteration,
+/// best first. The list is never empty, it holds a null move if there are no legal moves.
+#[allow(clippy::too_many_arguments)]
+pub fn iterative_deepening(
+    board: Board,
+    depth: u8,
+    color: Player,
+    alpha: i64,
+    beta: i64,
+    tt_table: &mut TranspositionTable,
+    do_null: bool,
+    eval_params: &Option<EvalParameters>,
+    timer: &Timer,
+    data: &mut ThreadData,
+    multi_pv: u8,
+    report: &dyn Fn(&SearchInfo),
+) -> Vec<RootMove> {
+    let legal_moves = board.generate_moves();
+    let num_lines = (multi_pv.max(1) as usize).min(legal_moves.len());
+    let mut lines: Vec<RootMove> = Vec::new();
+
+    'deepening: for d in 1..=depth {
+        //Always search the first iteration to have a move to fall back on
+        if d > 1 && skip_iteration(data.id, d) {
+            continue;
+        }
+
+        let iteration_start = timer.elapsed_millis();
+        let mut iteration: Vec<RootMove> = Vec::with_capacity(num_lines);
+        data.excluded_root_moves.clear();
+
+        for pv_idx in 0..num_lines {
+            data.seldepth = 0;
+            let (m, sc) = aspiration_search(
+                &board,
+                d,
+                color,
+                alpha,
+                beta,
+                lines.get(pv_idx).map(|line| line.score),
+                tt_table,
+                do_null,
+                eval_params,
+                timer,
+                data,
+            );
+            if timer.stopped() {
+                //An unfinished first iteration is still better than no move at all
+                if lines.is_empty() {
+                    if !m.is_null() {
+                        iteration.push(RootMove::new(m, sc, vec![m]));
+                    }
+                    lines = iteration;
+                }
+                break 'deepening;
+            }
+            if m.is_null() {
+                break;
+            }
+
+            data.excluded_root_moves.push(m);
+            iteration.push(RootMove::new(m, sc, data.pv.line()));
+
+            report(&SearchInfo {
+                depth: d,
+                seldepth: data.seldepth,
+                multipv: pv_idx as u8 + 1,
+                score: sc,
+                nodes: timer.nodes(),
+                time: timer.elapsed_millis(),
+                hashfull: tt_table.hashfull(),
+                pv: data.pv.line(),
+            });
+        }
+
+        iteration.sort_by_key(|line| Reverse(line.score));
+        lines = iteration;
+        data.completed_depth = d;
+
+        //Only the main thread manages the time, the helpers run until it stops them
+        if data.id == 0 && !timer.can_start_iteration(timer.elapsed_millis() - iteration_start) {
+            //Let the other threads know that the time for this move is up
+            timer.stop();
             break;
         }
-        mv = m;
-        latest_score = sc;
     }
+    data.excluded_root_moves.clear();
 
-    (mv, latest_score)
+    if lines.is_empty() {
+        let mv = legal_moves.first().copied().unwrap_or_else(BitMove::null);
+        lines.push(RootMove::new(mv, 0, vec![]));
+    }
+
+    lines
+}
+
+/// Picks the thread whose best move got the most votes. A thread votes for its best move
+/// with a weight growing with its score and its completed depth, ties go to the deepest
+/// thread. Returns the index of the chosen thread.
+fn vote_best_thread(results: &[(Vec<RootMove>, u8)]) -> usize {
+    let min_score = results
+        .iter()
+        .map(|(lines, _)| lines[0].score)
+        .min()
+        .unwrap_or(0);
+
+    let mut votes: HashMap<u16, i64> = HashMap::new();
+    for (lines, depth) in results {
+        *votes.entry(lines[0].mv.get_raw()).or_insert(0) +=
+            (lines[0].score - min_score + VOTE_SCORE_OFFSET) * *depth as i64;
+    }
+
+    let mut best = 0;
+    for (i, (lines, depth)) in results.iter().enumerate().skip(1) {
+        let (best_lines, best_depth) = &results[best];
+        let (vote, best_vote) = (
+            votes[&lines[0].mv.get_raw()],
+            votes[&best_lines[0].mv.get_raw()],
+        );
+        if vote > best_vote || (vote == best_vote && depth > best_depth) {
+            best = i;
+        }
+    }
+    best
 }
 
+/// Lazy SMP search with one thread per entry of `thread_data`, all sharing the
+/// transposition table. The helper threads skip some iterations to spread the threads
+/// over different depths and are stopped once the main thread is done. The thread data
+/// is kept between searches. Only the main thread reports its progress.
+///
+/// With a single line the move is voted for by all threads, with MultiPV the lines of
+/// the main thread are returned.
+#[allow(clippy::too_many_arguments)]
 pub fn search_parallel(
     board: Board,
     depth: u8,
     color: Player,
-    n_threads: u8,
-    max_time: u64,
-) -> (BitMove, i64) {
-    let timer = Timer::new(max_time);
-    let transposition_table = utils::new_tt_table();
+    multi_pv: u8,
+    transposition_table: &TranspositionTable,
+    thread_data: &mut Vec<ThreadData>,
+    timer: Timer,
+    report: InfoCallback,
+) -> Vec<RootMove> {
+    if thread_data.is_empty() {
+        thread_data.push(ThreadData::new());
+    }
+
+    let history = game_history(&board);
     let mut threads = Vec::new();
 
-    for _ in 0..n_threads {
+    for (i, mut data) in thread_data.drain(..).enumerate() {
         let b = board.parallel_clone();
         let mut tt_table = transposition_table.clone();
+        let timer = timer.clone();
+        let report = report.clone();
+        let history = history.clone();
         let handle = thread::spawn(move || {
-            alpha_beta(
+            let main_thread = i == 0;
+            data.id = i;
+            data.new_search();
+            data.history = history;
+            let lines = iterative_deepening(
                 b,
                 depth,
                 color,
-                -9999,
-                9999,
+                -INFINITY,
+                INFINITY,
                 &mut tt_table,
                 true,
                 &None,
                 &timer,
-            )
+                &mut data,
+                multi_pv,
+                &|info: &SearchInfo| {
+                    if main_thread {
+                        report(info)
+                    }
+                },
+            );
+            timer.flush_nodes();
+            if main_thread {
+                timer.stop();
+            }
+            (lines, data)
         });
         threads.push(handle);
     }
 
-    let mut best_mv = BitMove::null();
-    let mut best_score = 0;
-    for t in threads {
-        let (mv, s) = t.join().unwrap();
-        best_mv = mv;
-        best_score = s;
+    let mut results = Vec::new();
+    for handle in threads {
+        let (lines, data) = handle.join().unwrap();
+        results.push((lines, data.completed_depth));
+        thread_data.push(data);
     }
-    println!("table size {}", transposition_table.len());
-    (best_mv, best_score)
+
+    let best = if multi_pv > 1 {
+        0
+    } else {
+        vote_best_thread(&results)
+    };
+    results.swap_remove(best).0
 }
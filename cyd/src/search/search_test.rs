@@ -1,4 +1,5 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod search_test {
     use crate::search::*;
     use crate::utils;
@@ -13,7 +14,7 @@ mod search_test {
             board, depth, player, -9999, 9999, &mut tt, true, &None, &timer,
         );
         println!("depth: {}, move: {}, score: {}", depth, mv, score);
-        (String::from(mv.stringify()), score)
+        (mv.stringify(), score)
     }
 
     fn play_x_moves(fen: &str, depth: u8, plies: u8) -> Board {
@@ -38,6 +39,27 @@ mod search_test {
         board
     }
 
+    fn test_position_multi_pv(fen: &str, depth: u8, multi_pv: u8) -> Vec<RootMove> {
+        let mut tt = utils::new_tt_table();
+        let board = Board::from_fen(fen).unwrap();
+        let player = board.turn();
+        let timer = Timer::new(1000);
+        iterative_deepening(
+            board,
+            depth,
+            player,
+            -9999,
+            9999,
+            &mut tt,
+            true,
+            &None,
+            &timer,
+            &mut ThreadData::new(),
+            multi_pv,
+            &|_: &SearchInfo| {},
+        )
+    }
+
     #[test]
     fn queen_take_white_alpha_beta() {
         let fen = "2k5/8/4q3/8/2B5/8/8/1K6 w - - 0 1";
@@ -71,12 +93,122 @@ mod search_test {
     fn pin_knight_white_alpha_beta() {
         let fen = "2k4r/6pp/4n3/2p5/8/5B2/4PPPP/2K4R w - - 0 1";
 
-        for depth in 4..5 {
+        for depth in 5..6 {
             let (found_move, _) = test_position_alpha_beta(fen, depth);
             assert_eq!(found_move, "f3g4");
         }
     }
 
+    #[test]
+    fn multi_pv_distinct_ranked_lines() {
+        let fen = "2k5/8/4q3/8/2B5/8/8/1K6 w - - 0 1";
+        let lines = test_position_multi_pv(fen, 3, 3);
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].mv.stringify(), "c4e6");
+        assert_eq!(lines[0].pv[0], lines[0].mv);
+        assert!(lines[0].mv != lines[1].mv && lines[1].mv != lines[2].mv);
+        assert!(lines[0].mv != lines[2].mv);
+        assert!(lines[0].score >= lines[1].score && lines[1].score >= lines[2].score);
+    }
+
+    #[test]
+    fn multi_pv_capped_by_legal_moves() {
+        let fen = "k7/8/8/8/8/8/8/K6q w - - 0 1";
+        let lines = test_position_multi_pv(fen, 2, 10);
+        let legal = Board::from_fen(fen).unwrap().generate_moves().len();
+        assert_eq!(lines.len(), legal);
+    }
+
+    #[test]
+    fn lazy_smp_threads_agree_on_best_move() {
+        let board = Board::from_fen("2k4r/6pp/8/2p1n3/8/3N4/4PPPP/2K4R w - - 0 1").unwrap();
+        let mut thread_data: Vec<ThreadData> = (0..4).map(|_| ThreadData::new()).collect();
+        let lines = search_parallel(
+            board.clone(),
+            4,
+            board.turn(),
+            1,
+            &utils::new_tt_table(),
+            &mut thread_data,
+            Timer::new(1000),
+            std::sync::Arc::new(|_: &SearchInfo| {}),
+        );
+
+        assert_eq!(lines[0].mv.stringify(), "d3e5");
+        assert_eq!(thread_data.len(), 4);
+        //A helper stopped by the main thread in its first iteration completes no depth
+        assert!(thread_data[0].completed_depth > 0);
+        assert!(thread_data.iter().enumerate().all(|(i, data)| data.id == i));
+    }
+
+    #[test]
+    fn search_fills_move_ordering_tables() {
+        let board = Board::start_pos();
+        let mut data = ThreadData::new();
+        iterative_deepening(
+            board.clone(),
+            4,
+            board.turn(),
+            -INFINITY,
+            INFINITY,
+            &mut utils::new_tt_table(),
+            true,
+            &None,
+            &Timer::new(1000),
+            &mut data,
+            1,
+            &|_: &SearchInfo| {},
+        );
+
+        let moves = board.generate_moves();
+        assert!(moves
+            .iter()
+            .any(|mv| data.history_table.get(board.turn(), *mv) != 0));
+    }
+
+    #[test]
+    fn seldepth_counts_lines_without_quiescence() {
+        //The mate ends the line before quiescence is reached
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let seldepths = std::cell::RefCell::new(Vec::new());
+        iterative_deepening(
+            board.clone(),
+            4,
+            board.turn(),
+            -INFINITY,
+            INFINITY,
+            &mut utils::new_tt_table(),
+            true,
+            &None,
+            &Timer::new(1000),
+            &mut ThreadData::new(),
+            1,
+            &|info: &SearchInfo| seldepths.borrow_mut().push(info.seldepth),
+        );
+
+        let seldepths = seldepths.into_inner();
+        assert_eq!(seldepths.len(), 4);
+        assert!(seldepths.iter().all(|seldepth| *seldepth > 0));
+    }
+
+    #[test]
+    fn avoids_stalemate_when_winning() {
+        let fen = "k7/8/8/2Q5/8/8/8/1K6 w - - 0 1";
+        for depth in 1..4 {
+            let (found_move, score) = test_position_alpha_beta(fen, depth);
+            assert_ne!(found_move, "c5b6");
+            assert!(score > 0);
+        }
+    }
+
+    #[test]
+    fn draw_by_insufficient_material_scores_zero() {
+        let fen = "8/8/4k3/8/8/3K4/4N3/8 w - - 0 1";
+        let (_, score) = test_position_alpha_beta(fen, 3);
+        assert_eq!(score, 0);
+    }
+
     #[test]
     fn mate_in_one_white() {
         let fen = "k7/5R2/6R1/8/8/8/4K3/8 w - - 0 1";
@@ -87,6 +219,35 @@ mod search_test {
         }
     }
 
+    #[test]
+    fn mate_in_one_score() {
+        let fen = "k7/5R2/6R1/8/8/8/4K3/8 w - - 0 1";
+        for depth in 1..4 {
+            let (_, score) = test_position_alpha_beta(fen, depth);
+            assert_eq!(score, mate_in(1));
+        }
+    }
+
+    #[test]
+    fn mate_distance_survives_transposition_table() {
+        let board = Board::from_fen("k7/4R3/8/8/8/4R3/8/3K4 w - - 0 1").unwrap();
+        let mut tt = utils::new_tt_table();
+        for _ in 0..2 {
+            let (_, score) = alpha_beta(
+                board.clone(),
+                4,
+                board.turn(),
+                -INFINITY,
+                INFINITY,
+                &mut tt,
+                true,
+                &None,
+                &Timer::new(1000),
+            );
+            assert_eq!(Score::from_value(score), Score::Mate(2));
+        }
+    }
+
     #[test]
     fn mate_in_two_white() {
         let fen = "k7/4R3/8/8/8/4R3/8/3K4 w - - 0 1";
@@ -173,6 +334,35 @@ mod search_test {
         assert_eq!("e2e3", found_move2);
     }
 
+    #[test]
+    fn check_extension_sees_mate_behind_recapture() {
+        //Re8+ Rxe8 Rxe8#, found at every depth because checks are not cut at the horizon
+        let fen = "3r3k/6pp/8/8/8/8/4R3/4R1K1 w - - 0 1";
+        for depth in 1..=3 {
+            let (mv, score) = test_position_alpha_beta(fen, depth);
+            assert_eq!("e2e8", mv);
+            assert_eq!(mate_in(3), score);
+        }
+    }
+
+    #[test]
+    fn evades_check_without_losing_queen() {
+        let fen = "4k3/3q4/8/8/8/8/8/4R1K1 b - - 0 1";
+        for depth in 1..=3 {
+            let (mv, _) = test_position_alpha_beta(fen, depth);
+            assert!(mv.starts_with("e8"), "{} blocks the check", mv);
+        }
+    }
+
+    #[test]
+    fn mate_in_three_with_singular_extensions() {
+        //Deep enough for the TT moves of the mating line to be tested for singularity
+        let fen = "4r2k/pp4pp/3Q1b2/1n6/4pN2/B3PqP1/5P2/6KR w - - 0 35";
+        let (mv, score) = test_position_alpha_beta(fen, 6);
+        assert_eq!("f4g6", mv);
+        assert!(score >= MATE_IN_MAX_PLY);
+    }
+
     #[test]
     #[ignore]
     fn mate_in_four() {
@@ -0,0 +1,168 @@
+use crate::evaluate::piece_values;
+use pleco::{BitBoard, BitMove, Board, PieceType, Player, SQ};
+
+//Attackers are tried from the least to the most valuable
+const ATTACKER_ORDER: [PieceType; 6] = [
+    PieceType::P,
+    PieceType::N,
+    PieceType::B,
+    PieceType::R,
+    PieceType::Q,
+    PieceType::K,
+];
+const MAX_EXCHANGES: usize = 32;
+
+fn value(piece: PieceType) -> i64 {
+    piece_values(piece) as i64
+}
+
+/// The piece captured by `mv`, a pawn for en passant captures.
+fn captured_piece(board: &Board, mv: BitMove) -> PieceType {
+    if mv.is_en_passant() {
+        PieceType::P
+    } else {
+        board.piece_at_sq(mv.get_dest()).type_of()
+    }
+}
+
+/// Material won by the capture `mv` before any recapture.
+pub fn capture_value(board: &Board, mv: BitMove) -> i64 {
+    value(captured_piece(board, mv))
+}
+
+/// Most valuable victim, least valuable attacker. Orders captures of the most valuable
+/// pieces first and, for the same victim, the captures with the cheapest pieces first.
+pub fn mvv_lva(board: &Board, mv: BitMove) -> u32 {
+    let victim = captured_piece(board, mv);
+    let attacker = board.piece_at_sq(mv.get_src()).type_of();
+    let rank = |piece: PieceType| ATTACKER_ORDER.iter().position(|p| *p == piece).unwrap_or(0);
+    (rank(victim) * ATTACKER_ORDER.len() + ATTACKER_ORDER.len() - rank(attacker)) as u32
+}
+
+/// The least valuable piece of `player` in `attackers`, with its square.
+fn least_valuable_attacker(
+    board: &Board,
+    attackers: u64,
+    player: Player,
+) -> Option<(PieceType, u64)> {
+    ATTACKER_ORDER.iter().find_map(|piece| {
+        let pieces = attackers & board.piece_bb(player, *piece).0;
+        if pieces == 0 {
+            None
+        } else {
+            Some((*piece, pieces & pieces.wrapping_neg()))
+        }
+    })
+}
+
+/// Static exchange evaluation, the material won by `mv` when both sides keep capturing
+/// on the destination square with their least valuable piece and may stop whenever
+/// continuing loses material. Pieces behind a capturing slider join the exchange, pins
+/// are ignored.
+pub fn see(board: &Board, mv: BitMove) -> i64 {
+    let from = mv.get_src();
+    let to = mv.get_dest();
+
+    let mut gain = [0i64; MAX_EXCHANGES];
+    let mut on_square = board.piece_at_sq(from).type_of();
+    let mut occupied = board.occupied().0 ^ from.to_bb().0;
+
+    if mv.is_capture() {
+        gain[0] = value(captured_piece(board, mv));
+    }
+    if mv.is_en_passant() {
+        //The captured pawn is behind the destination square
+        let captured = match board.turn() {
+            Player::White => to.0 - 8,
+            Player::Black => to.0 + 8,
+        };
+        occupied ^= SQ(captured).to_bb().0;
+    }
+    if mv.is_promo() {
+        on_square = mv.promo_piece();
+        gain[0] += value(on_square) - value(PieceType::P);
+    }
+
+    let mut side = board.turn().other_player();
+    let mut depth = 0;
+    loop {
+        let attackers = board.attackers_to(to, BitBoard(occupied)).0 & occupied;
+        let (piece, square) = match least_valuable_attacker(board, attackers, side) {
+            Some(attacker) => attacker,
+            None => break,
+        };
+
+        depth += 1;
+        gain[depth] = value(on_square) - gain[depth - 1];
+        //Neither side can gain by continuing the exchange
+        if (-gain[depth - 1]).max(gain[depth]) < 0 || depth + 1 == MAX_EXCHANGES {
+            break;
+        }
+
+        on_square = piece;
+        occupied ^= square;
+        side = side.other_player();
+    }
+
+    while depth > 0 {
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        depth -= 1;
+    }
+    gain[0]
+}
+
+/// Whether the static exchange evaluation of `mv` is at least `threshold`.
+pub fn see_ge(board: &Board, mv: BitMove, threshold: i64) -> bool {
+    see(board, mv) >= threshold
+}
+
+#[cfg(test)]
+mod see_test {
+    use super::*;
+    use crate::utils::find_move;
+
+    fn see_of(fen: &str, uci: &str) -> i64 {
+        let board = Board::from_fen(fen).unwrap();
+        see(&board, find_move(&board, uci))
+    }
+
+    #[test]
+    fn undefended_piece() {
+        assert_eq!(see_of("4k3/8/8/3q4/4P3/8/8/4K3 w - - 0 1", "e4d5"), 929);
+    }
+
+    #[test]
+    fn defended_pawn_costs_the_attacker() {
+        assert_eq!(
+            see_of("4k3/8/5n2/3p4/8/8/3Q4/4K3 w - - 0 1", "d2d5"),
+            100 - 929
+        );
+        assert_eq!(
+            see_of("4k3/8/3p4/4p3/8/8/8/K3R3 w - - 0 1", "e1e5"),
+            100 - 479
+        );
+    }
+
+    #[test]
+    fn x_ray_attacker_joins() {
+        assert_eq!(see_of("4k3/4r3/8/4p3/8/8/4R3/K3R3 w - - 0 1", "e2e5"), 100);
+    }
+
+    #[test]
+    fn quiet_move_to_attacked_square() {
+        assert_eq!(see_of("4k3/8/3p4/8/8/8/8/K3R3 w - - 0 1", "e1e5"), -479);
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/K3R3 w - - 0 1").unwrap();
+        assert!(see_ge(&board, find_move(&board, "e1e5"), 0));
+    }
+
+    #[test]
+    fn mvv_lva_order() {
+        let board = Board::from_fen("4k3/8/8/3q4/n1P5/8/8/3QK3 w - - 0 1").unwrap();
+        let pawn_takes_queen = mvv_lva(&board, find_move(&board, "c4d5"));
+        let queen_takes_queen = mvv_lva(&board, find_move(&board, "d1d5"));
+        let queen_takes_knight = mvv_lva(&board, find_move(&board, "d1a4"));
+        assert!(pawn_takes_queen > queen_takes_queen);
+        assert!(queen_takes_queen > queen_takes_knight);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::evaluate::PawnTable;
+use crate::search::ordering::{CounterMoves, HistoryTable, KillerMoves};
+use crate::search::parameters::SearchParameters;
+use crate::search::pv::{PvTable, MAX_PLY};
+use pleco::BitMove;
+
+/// State owned by a single search thread, kept from one search to the next.
+pub struct ThreadData {
+    pub id: usize, //0 for the main thread
+    pub seldepth: u8,
+    pub pv: PvTable,
+    pub excluded_root_moves: Vec<BitMove>, //Lines already found in a MultiPV iteration
+    pub excluded_moves: [BitMove; MAX_PLY], //TT move skipped by a singular extension search
+    pub extensions: [u8; MAX_PLY],         //Plies of extensions on the path to each ply
+    pub completed_depth: u8,
+    pub history: Vec<u64>, //Keys of the game positions before the root and of the search path
+    pub killers: KillerMoves,
+    pub history_table: HistoryTable,
+    pub counter_moves: CounterMoves,
+    pub pawn_table: PawnTable,
+    pub params: SearchParameters,
+}
+
+impl ThreadData {
+    pub fn new() -> Self {
+        Self::with_params(SearchParameters::default())
+    }
+
+    pub fn with_params(params: SearchParameters) -> Self {
+        Self {
+            id: 0,
+            seldepth: 0,
+            pv: PvTable::new(),
+            excluded_root_moves: Vec::new(),
+            excluded_moves: [BitMove::null(); MAX_PLY],
+            extensions: [0; MAX_PLY],
+            completed_depth: 0,
+            history: Vec::new(),
+            killers: KillerMoves::new(),
+            history_table: HistoryTable::new(),
+            counter_moves: CounterMoves::new(),
+            pawn_table: PawnTable::new(),
+            params,
+        }
+    }
+
+    /// Resets the statistics of the previous search, the move ordering tables are kept.
+    pub fn new_search(&mut self) {
+        self.seldepth = 0;
+        self.excluded_root_moves.clear();
+        self.completed_depth = 0;
+        self.history_table.age();
+    }
+}
+
+impl Default for ThreadData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,20 +1,338 @@
+use pleco::Player;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
 
-#[derive(Copy, Clone)]
+const MOVE_OVERHEAD: u64 = 30; //milliseconds kept in reserve for communication
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+const MAX_MOVES_TO_GO: u64 = 50;
+const HARD_LIMIT_FACTOR: u64 = 4;
+const MIN_SEARCH_TIME: u64 = 10;
+const BRANCHING_ESTIMATE: u64 = 2; //Estimated time ratio between two consecutive iterations
+const POLL_INTERVAL: u32 = 1024; //Nodes between two reads of the clock
+
+/// The clock state sent by a GUI or server, all times in milliseconds, and the number
+/// of nodes the search may use.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct TimeControl {
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: u64,
+    pub binc: u64,
+    pub movestogo: Option<u64>,
+    pub movetime: Option<u64>,
+    pub nodes: Option<u64>,
+}
+
+impl TimeControl {
+    pub fn move_time(movetime: u64) -> Self {
+        Self {
+            movetime: Some(movetime),
+            ..Self::default()
+        }
+    }
+
+    /// Soft and hard limits in milliseconds for the player to move, `None` if there is no limit.
+    ///
+    /// The soft limit is the time we aim to spend on the move, no new iteration is started
+    /// after it. The hard limit aborts the search in the middle of an iteration.
+    pub fn limits(&self, player: Player) -> Option<(u64, u64)> {
+        if let Some(movetime) = self.movetime {
+            let time = movetime.saturating_sub(MOVE_OVERHEAD).max(MIN_SEARCH_TIME);
+            return Some((time, time));
+        }
+
+        let (time_left, inc) = match player {
+            Player::White => (self.wtime?, self.winc),
+            Player::Black => (self.btime?, self.binc),
+        };
+
+        let available = time_left.saturating_sub(MOVE_OVERHEAD).max(MIN_SEARCH_TIME);
+        let moves_to_go = self
+            .movestogo
+            .unwrap_or(DEFAULT_MOVES_TO_GO)
+            .clamp(1, MAX_MOVES_TO_GO);
+
+        let soft = (available / moves_to_go + inc * 3 / 4).min(available);
+        let hard = (soft * HARD_LIMIT_FACTOR).min(available / 2).max(soft);
+        Some((soft.max(MIN_SEARCH_TIME), hard.max(MIN_SEARCH_TIME)))
+    }
+}
+
+/// Keeps track of the time spent searching, the nodes searched and of the stop signal.
+///
+/// Clones share the stop flag and the node count, so stopping any clone (from a protocol
+/// handler or from a search thread running out of time or nodes) stops every thread
+/// using the timer.
+///
+/// A pondering timer ignores its time limits until `ponder_hit`, the limits then count
+/// from the ponder hit since that is when the clock of the engine starts running.
+#[derive(Clone)]
 pub struct Timer {
     start: Instant,
-    max_time: u64, //seconds
+    soft_limit: u64, //milliseconds
+    hard_limit: u64, //milliseconds
+    node_limit: u64,
+    stop: Arc<AtomicBool>,
+    pondering: Arc<AtomicBool>,
+    ponder_hit_time: Arc<AtomicU64>, //milliseconds after the start
+    nodes: Arc<AtomicU64>,           //Nodes of every thread, added in batches of POLL_INTERVAL
+    polls: Cell<u32>,                //Nodes of this thread not yet added to `nodes`
 }
 
 impl Timer {
     pub fn new(max_time: u64) -> Self {
+        Self::from_millis(max_time * 1000, max_time * 1000)
+    }
+
+    pub fn from_millis(soft_limit: u64, hard_limit: u64) -> Self {
         Self {
             start: Instant::now(),
-            max_time,
+            soft_limit,
+            hard_limit,
+            node_limit: u64::MAX,
+            stop: Arc::new(AtomicBool::new(false)),
+            pondering: Arc::new(AtomicBool::new(false)),
+            ponder_hit_time: Arc::new(AtomicU64::new(0)),
+            nodes: Arc::new(AtomicU64::new(0)),
+            polls: Cell::new(0),
+        }
+    }
+
+    /// Stops the search once every thread together has searched `nodes` nodes.
+    pub fn with_node_limit(mut self, nodes: u64) -> Self {
+        self.node_limit = nodes;
+        self
+    }
+
+    pub fn infinite() -> Self {
+        Self::from_millis(u64::MAX, u64::MAX)
+    }
+
+    pub fn from_time_control(time_control: &TimeControl, player: Player) -> Self {
+        let timer = match time_control.limits(player) {
+            Some((soft, hard)) => Self::from_millis(soft, hard),
+            None => Self::infinite(),
+        };
+        match time_control.nodes {
+            Some(nodes) => timer.with_node_limit(nodes),
+            None => timer,
         }
     }
 
+    /// Searches on the opponent's time, without a time limit until `ponder_hit`.
+    pub fn ponder(self) -> Self {
+        self.pondering.store(true, Ordering::Relaxed);
+        self
+    }
+
+    pub fn pondering(&self) -> bool {
+        self.pondering.load(Ordering::Relaxed)
+    }
+
+    /// The opponent played the expected move, the time limits apply from now on.
+    pub fn ponder_hit(&self) {
+        self.ponder_hit_time
+            .store(self.elapsed_millis(), Ordering::Relaxed);
+        self.pondering.store(false, Ordering::Relaxed);
+    }
+
+    pub fn elapsed_millis(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    /// Time counted against the limits, the time spent pondering is free.
+    fn used_millis(&self) -> u64 {
+        self.elapsed_millis()
+            .saturating_sub(self.ponder_hit_time.load(Ordering::Relaxed))
+    }
+
+    /// True once the hard limit is reached and the search must be aborted.
     pub fn elapsed(&self) -> bool {
-        self.start.elapsed().as_secs() >= self.max_time
+        !self.pondering() && self.used_millis() >= self.hard_limit
+    }
+
+    /// Signals every search using this timer to unwind as soon as possible.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Called once per node. Every `POLL_INTERVAL` calls the nodes are added to the count
+    /// shared by all threads, and the stop flag is raised when the hard limit or the
+    /// node limit is reached. Returns true if the search should stop.
+    pub fn poll(&self) -> bool {
+        let polls = self.polls.get() + 1;
+        if polls < POLL_INTERVAL {
+            self.polls.set(polls);
+        } else {
+            self.polls.set(0);
+            let nodes = self.nodes.fetch_add(polls as u64, Ordering::Relaxed) + polls as u64;
+            if nodes >= self.node_limit || self.elapsed() {
+                self.stop();
+            }
+        }
+        self.stopped()
+    }
+
+    /// Nodes searched by every thread using the timer. The last nodes of the other
+    /// threads are only included once they fill a batch or the thread flushes them.
+    pub fn nodes(&self) -> u64 {
+        self.nodes.load(Ordering::Relaxed) + self.polls.get() as u64
+    }
+
+    /// Adds the nodes of this thread that do not fill a batch to the shared count, a
+    /// thread calls it when it is done so that the count is exact after the search.
+    pub fn flush_nodes(&self) {
+        self.nodes
+            .fetch_add(self.polls.replace(0) as u64, Ordering::Relaxed);
+    }
+
+    /// Whether a new iteration is likely to finish, given how long the last one took.
+    pub fn can_start_iteration(&self, last_iteration: u64) -> bool {
+        if self.stopped() {
+            return false;
+        }
+        if self.pondering() {
+            return true;
+        }
+        let elapsed = self.used_millis();
+        if self.hard_limit == u64::MAX {
+            return elapsed < self.soft_limit;
+        }
+        elapsed < self.soft_limit
+            && elapsed.saturating_add(last_iteration.saturating_mul(BRANCHING_ESTIMATE))
+                < self.hard_limit
+    }
+}
+
+#[cfg(test)]
+mod timer_test {
+    use super::*;
+
+    #[test]
+    fn no_clock_no_limit() {
+        assert_eq!(TimeControl::default().limits(Player::White), None);
+    }
+
+    #[test]
+    fn move_time_limit() {
+        let tc = TimeControl::move_time(1000);
+        assert_eq!(tc.limits(Player::Black), Some((970, 970)));
+    }
+
+    #[test]
+    fn clock_limits_use_side_to_move() {
+        let tc = TimeControl {
+            wtime: Some(60_000),
+            btime: Some(3_000),
+            winc: 1_000,
+            binc: 1_000,
+            ..TimeControl::default()
+        };
+        let (white_soft, white_hard) = tc.limits(Player::White).unwrap();
+        let (black_soft, black_hard) = tc.limits(Player::Black).unwrap();
+
+        assert!(white_soft > black_soft);
+        assert!(white_soft <= white_hard);
+        assert!(black_soft <= black_hard);
+        assert!(black_hard < 3_000);
+    }
+
+    #[test]
+    fn moves_to_go_spends_more_time() {
+        let mut tc = TimeControl {
+            wtime: Some(60_000),
+            ..TimeControl::default()
+        };
+        let (soft, _) = tc.limits(Player::White).unwrap();
+        tc.movestogo = Some(5);
+        let (soft_few_moves, _) = tc.limits(Player::White).unwrap();
+        assert!(soft_few_moves > soft);
+    }
+
+    #[test]
+    fn stop_is_shared_between_clones() {
+        let timer = Timer::infinite();
+        let search_timer = timer.clone();
+        assert!(!search_timer.poll());
+
+        timer.stop();
+        assert!(search_timer.stopped());
+        assert!(search_timer.poll());
+        assert!(!search_timer.can_start_iteration(0));
+    }
+
+    #[test]
+    fn poll_stops_on_hard_limit() {
+        let timer = Timer::from_millis(0, 0);
+        let stopped = (0..POLL_INTERVAL).any(|_| timer.poll());
+        assert!(stopped);
+    }
+
+    #[test]
+    fn node_limit_is_shared_between_clones() {
+        let timer = Timer::infinite().with_node_limit(2 * POLL_INTERVAL as u64);
+        let other = timer.clone();
+        assert!(!(0..POLL_INTERVAL).any(|_| timer.poll()));
+        assert_eq!(other.nodes(), POLL_INTERVAL as u64);
+        assert!((0..POLL_INTERVAL).any(|_| other.poll()));
+        assert!(timer.stopped());
+        assert_eq!(timer.nodes(), 2 * POLL_INTERVAL as u64);
+    }
+
+    #[test]
+    fn flushed_nodes_are_shared() {
+        let timer = Timer::infinite();
+        let other = timer.clone();
+        for _ in 0..10 {
+            other.poll();
+        }
+        assert_eq!(timer.nodes(), 0);
+        other.flush_nodes();
+        assert_eq!(timer.nodes(), 10);
+        assert_eq!(other.nodes(), 10);
+    }
+
+    #[test]
+    fn node_limit_from_go_command() {
+        let tc = TimeControl {
+            nodes: Some(5000),
+            ..TimeControl::default()
+        };
+        assert_eq!(tc.limits(Player::White), None);
+        let timer = Timer::from_time_control(&tc, Player::White);
+        assert!((0..5 * POLL_INTERVAL).any(|_| timer.poll()));
+    }
+
+    #[test]
+    fn pondering_ignores_time_until_ponder_hit() {
+        let timer = Timer::from_millis(0, 0).ponder();
+        assert!(!(0..2 * POLL_INTERVAL).any(|_| timer.poll()));
+        assert!(timer.can_start_iteration(u64::MAX));
+
+        timer.clone().ponder_hit();
+        assert!(!timer.pondering());
+        assert!(timer.elapsed());
+        assert!(!timer.can_start_iteration(0));
+    }
+
+    #[test]
+    fn ponder_hit_restarts_the_clock() {
+        let timer = Timer::from_millis(50, 60_000).ponder();
+        std::thread::sleep(std::time::Duration::from_millis(60));
+        timer.ponder_hit();
+        assert!(timer.can_start_iteration(0));
+    }
+
+    #[test]
+    fn infinite_timer_never_elapses() {
+        let timer = Timer::infinite();
+        assert!(!timer.elapsed());
+        assert!(timer.can_start_iteration(u64::MAX));
     }
 }
@@ -1,6 +1,4 @@
-use evmap_derive::ShallowCopy;
-
-#[derive(PartialEq, Copy, Clone, Hash, ShallowCopy)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum EntryFlag {
     Exact,
     LowerBound,
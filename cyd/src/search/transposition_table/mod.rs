@@ -3,5 +3,5 @@ mod table;
 mod transposition_entry;
 
 pub use entry_flag::*;
-pub use table::TranspositionTable;
+pub use table::{TranspositionTable, DEFAULT_HASH_SIZE};
 pub use transposition_entry::*;
@@ -1,83 +1,331 @@
-use crate::search::transposition_table::TtEntry;
-use evmap;
-use fnv;
+use crate::search::transposition_table::{EntryFlag, TtEntry};
+use pleco::BitMove;
 
-use std::clone::Clone;
-use std::hash::BuildHasherDefault;
-use std::sync::{Arc, Mutex};
+use std::mem;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
 
+pub const DEFAULT_HASH_SIZE: usize = 16; //MB
+const ENTRIES_PER_BUCKET: usize = 4;
+const GENERATION_MASK: u8 = 0b11_1111; //The age is stored in six bits
+const AGE_WEIGHT: i32 = 4; //Plies of depth one search of age is worth when replacing
+
+/// A single entry packed into one word. The key is stored xor:ed with the data, so an
+/// entry torn by a concurrent write does not verify and reads as a miss.
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+#[derive(Default)]
+#[repr(align(64))]
+struct Bucket {
+    slots: [Slot; ENTRIES_PER_BUCKET],
+}
+
+/// Fixed size hash table shared between search threads without locking.
+///
+/// Clones share the same underlying memory.
 pub struct TranspositionTable {
-    reader: evmap::ReadHandle<u64, TtEntry, (), BuildHasherDefault<fnv::FnvHasher>>,
-    #[allow(clippy::type_complexity)]
-    writer: Arc<Mutex<evmap::WriteHandle<u64, TtEntry, (), BuildHasherDefault<fnv::FnvHasher>>>>,
+    buckets: Arc<[Bucket]>,
+    mask: usize,
+    generation: Arc<AtomicU8>,
+}
+
+fn flag_bits(flag: EntryFlag) -> u64 {
+    match flag {
+        EntryFlag::Exact => 1,
+        EntryFlag::LowerBound => 2,
+        EntryFlag::UpperBound => 3,
+    }
+}
+
+fn pack(entry: &TtEntry) -> u64 {
+    let value = entry.value.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+    (entry.mv.get_raw() as u64)
+        | ((entry.depth as u64) << 16)
+        | (flag_bits(entry.flag) << 24)
+        | (((entry.age & GENERATION_MASK) as u64) << 26)
+        | ((value as u32 as u64) << 32)
+}
+
+fn unpack(data: u64) -> Option<TtEntry> {
+    let flag = match (data >> 24) & 0b11 {
+        1 => EntryFlag::Exact,
+        2 => EntryFlag::LowerBound,
+        3 => EntryFlag::UpperBound,
+        _ => return None,
+    };
+    Some(TtEntry {
+        mv: BitMove::new(data as u16),
+        depth: (data >> 16) as u8,
+        flag,
+        value: (data >> 32) as u32 as i32 as i64,
+        age: (data >> 26) as u8 & GENERATION_MASK,
+    })
 }
 
 impl TranspositionTable {
-    pub fn new() -> Self {
-        let opt = evmap::Options::default();
-        let mp = opt.with_hasher(BuildHasherDefault::<fnv::FnvHasher>::default());
-        let (r, w) = mp.construct();
+    /// Creates a table using at most `size_mb` megabytes, rounded down to a power of two
+    /// number of buckets.
+    pub fn new(size_mb: usize) -> Self {
+        let bytes = size_mb.max(1) * 1024 * 1024;
+        let max_buckets = (bytes / mem::size_of::<Bucket>()).max(1);
+        let num_buckets = if max_buckets.is_power_of_two() {
+            max_buckets
+        } else {
+            max_buckets.next_power_of_two() / 2
+        };
+
+        let buckets: Vec<Bucket> = (0..num_buckets).map(|_| Bucket::default()).collect();
         Self {
-            reader: r,
-            writer: Arc::new(Mutex::new(w)),
+            buckets: buckets.into(),
+            mask: num_buckets - 1,
+            generation: Arc::new(AtomicU8::new(0)),
         }
     }
 
-    pub fn get(&self, val: &u64) -> Option<evmap::ReadGuard<TtEntry>> {
-        self.reader.get_one(val)
+    /// The age new entries are stored with.
+    pub fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
     }
 
-    fn convert(&self, value: evmap::ReadGuard<TtEntry>) -> TtEntry {
-        TtEntry {
-            mv: value.mv,
-            depth: value.depth,
-            flag: value.flag,
-            value: value.value,
-        }
+    /// Starts a new search, entries from earlier searches become stale and are replaced first.
+    pub fn new_search(&self) {
+        let next = self.generation().wrapping_add(1) & GENERATION_MASK;
+        self.generation.store(next, Ordering::Relaxed);
+    }
+
+    /// Searches since the entry was stored.
+    fn relative_age(&self, entry: &TtEntry) -> u8 {
+        self.generation().wrapping_sub(entry.age) & GENERATION_MASK
+    }
+
+    fn bucket(&self, key: u64) -> &Bucket {
+        &self.buckets[key as usize & self.mask]
     }
 
-    fn get_and_convert(&self, val: &u64) -> Option<TtEntry> {
-        self.get(val).map(|value| self.convert(value))
+    pub fn get(&self, key: &u64) -> Option<TtEntry> {
+        self.bucket(*key).slots.iter().find_map(|slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            if data != 0 && slot.key.load(Ordering::Relaxed) ^ data == *key {
+                unpack(data)
+            } else {
+                None
+            }
+        })
     }
 
-    pub fn insert(&mut self, key: u64, val: TtEntry) {
-        match self.get_and_convert(&key) {
-            Some(value) if value != val => {} //If the value is already present, no need to insert it again
-            _ => {
-                let mut writer = self.writer.lock().unwrap();
-                writer.insert(key, val);
-                writer.refresh();
+    /// Stores the entry, replacing an entry for the same position if there is one,
+    /// otherwise an empty slot or the slot with the shallowest and oldest search.
+    pub fn insert(&self, key: u64, mut val: TtEntry) {
+        let bucket = self.bucket(key);
+
+        let mut replace = &bucket.slots[0];
+        let mut replace_priority = i32::MAX;
+        for slot in bucket.slots.iter() {
+            let data = slot.data.load(Ordering::Relaxed);
+            let existing = unpack(data);
+
+            if data != 0 && slot.key.load(Ordering::Relaxed) ^ data == key {
+                //Keep the known best move when the new search did not find one
+                if let Some(old) = existing {
+                    if val.mv.is_null() {
+                        val.mv = old.mv;
+                    }
+                }
+                replace = slot;
+                break;
+            }
+
+            let priority = match existing {
+                Some(old) => old.depth as i32 - AGE_WEIGHT * self.relative_age(&old) as i32,
+                None => {
+                    replace = slot;
+                    break;
+                }
+            };
+            if priority < replace_priority {
+                replace = slot;
+                replace_priority = priority;
             }
         }
+
+        let data = pack(&val);
+        replace.data.store(data, Ordering::Relaxed);
+        replace.key.store(key ^ data, Ordering::Relaxed);
     }
 
-    pub fn insert_no_refresh(&mut self, key: u64, val: TtEntry) {
-        let mut writer = self.writer.lock().unwrap();
-        writer.insert(key, val);
+    pub fn clear(&self) {
+        for slot in self.buckets.iter().flat_map(|bucket| bucket.slots.iter()) {
+            slot.data.store(0, Ordering::Relaxed);
+            slot.key.store(0, Ordering::Relaxed);
+        }
     }
 
-    pub fn refresh(&mut self) {
-        let mut writer = self.writer.lock().unwrap();
-        writer.refresh();
+    /// Fill level in permille of entries from the current search, estimated from the
+    /// first thousand entries.
+    pub fn hashfull(&self) -> u16 {
+        let sample = self
+            .buckets
+            .iter()
+            .flat_map(|bucket| bucket.slots.iter())
+            .take(1000);
+
+        let mut total = 0;
+        let mut used = 0;
+        for slot in sample {
+            total += 1;
+            match unpack(slot.data.load(Ordering::Relaxed)) {
+                Some(entry) if entry.age == self.generation() => used += 1,
+                _ => {}
+            }
+        }
+        (used * 1000 / total) as u16
     }
 
-    #[allow(clippy::len_without_is_empty)] //is_empty never used
-    pub fn len(&self) -> usize {
-        self.reader.len()
+    /// Number of entries the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * ENTRIES_PER_BUCKET
     }
 }
 
 impl Default for TranspositionTable {
     fn default() -> Self {
-        Self::new()
+        Self::new(DEFAULT_HASH_SIZE)
     }
 }
 
 impl Clone for TranspositionTable {
     fn clone(&self) -> Self {
         Self {
-            writer: self.writer.clone(),
-            reader: self.reader.clone(),
+            buckets: self.buckets.clone(),
+            mask: self.mask,
+            generation: self.generation.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod table_test {
+    use super::*;
+
+    fn entry(depth: u8, value: i64) -> TtEntry {
+        TtEntry {
+            mv: BitMove::new(0x1234),
+            depth,
+            flag: EntryFlag::LowerBound,
+            value,
+            age: 0,
+        }
+    }
+
+    #[test]
+    fn size_is_power_of_two() {
+        let tt = TranspositionTable::new(3);
+        let buckets = tt.capacity() / ENTRIES_PER_BUCKET;
+        assert!(buckets.is_power_of_two());
+        assert!(buckets * mem::size_of::<Bucket>() <= 3 * 1024 * 1024);
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let tt = TranspositionTable::new(1);
+        tt.insert(42, entry(5, -1234));
+        assert_eq!(tt.get(&42), Some(entry(5, -1234)));
+        assert_eq!(tt.get(&43), None);
+    }
+
+    #[test]
+    fn clones_share_entries() {
+        let tt = TranspositionTable::new(1);
+        let clone = tt.clone();
+        clone.insert(7, entry(3, 9999));
+        assert_eq!(tt.get(&7), Some(entry(3, 9999)));
+    }
+
+    #[test]
+    fn replaces_shallowest_entry() {
+        let tt = TranspositionTable::new(1);
+        let stride = (tt.mask + 1) as u64;
+        for i in 0..ENTRIES_PER_BUCKET as u64 {
+            tt.insert(1 + i * stride, entry(10 + i as u8, 0));
+        }
+        tt.insert(1 + 10 * stride, entry(1, 0));
+
+        assert_eq!(tt.get(&1), None);
+        assert!(tt.get(&(1 + stride)).is_some());
+        assert!(tt.get(&(1 + 10 * stride)).is_some());
+    }
+
+    #[test]
+    fn replaces_stale_entry() {
+        let tt = TranspositionTable::new(1);
+        let stride = (tt.mask + 1) as u64;
+        tt.insert(1, entry(8, 0));
+
+        tt.new_search();
+        let fresh = |depth| TtEntry {
+            age: tt.generation(),
+            ..entry(depth, 0)
+        };
+        for i in 1..=ENTRIES_PER_BUCKET as u64 {
+            tt.insert(1 + i * stride, fresh(6));
+        }
+
+        assert_eq!(tt.get(&1), None);
+        for i in 1..=ENTRIES_PER_BUCKET as u64 {
+            assert!(tt.get(&(1 + i * stride)).is_some());
+        }
+    }
+
+    #[test]
+    fn generation_wraps_around() {
+        let tt = TranspositionTable::new(1);
+        for _ in 0..=GENERATION_MASK {
+            tt.new_search();
+        }
+        assert_eq!(tt.generation(), 0);
+    }
+
+    #[test]
+    fn hashfull_counts_current_search() {
+        let tt = TranspositionTable::new(1);
+        for key in 0..(tt.capacity() as u64) {
+            tt.insert(key, entry(1, 0));
+        }
+        assert_eq!(tt.hashfull(), 1000);
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn clear_and_hashfull() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+
+        for key in 0..(tt.capacity() as u64 * 2) {
+            tt.insert(key, entry(1, 0));
         }
+        assert_eq!(tt.hashfull(), 1000);
+
+        tt.clear();
+        assert_eq!(tt.hashfull(), 0);
+        assert_eq!(tt.get(&1), None);
+    }
+
+    #[test]
+    fn keeps_move_of_same_position() {
+        let tt = TranspositionTable::new(1);
+        tt.insert(5, entry(2, 0));
+        tt.insert(
+            5,
+            TtEntry {
+                mv: BitMove::null(),
+                ..entry(4, 10)
+            },
+        );
+        assert_eq!(tt.get(&5), Some(entry(4, 10)));
     }
 }
@@ -1,33 +1,44 @@
-use crate::search::transposition_table::{EntryFlag, TranspositionTable, TtEntry};
+use crate::search::transposition_table::{
+    EntryFlag, TranspositionTable, TtEntry, DEFAULT_HASH_SIZE,
+};
 use pleco::BitMove;
 use std::collections::HashMap;
 use std::fs;
 
-fn parse_opening_book() -> Result<TranspositionTable, std::io::Error> {
+fn parse_opening_book() -> Result<HashMap<u64, (u16, bool)>, std::io::Error> {
     let file = fs::read_to_string("../opening_book.json")?;
-    let interim_book: HashMap<u64, (u16, bool)> = serde_json::from_str(&file)?;
+    let book: HashMap<u64, (u16, bool)> = serde_json::from_str(&file)?;
+    Ok(book)
+}
 
-    let mut book = TranspositionTable::new();
+/// Stores the opening book moves, if the book file is available, in the table.
+pub fn add_opening_book(tt_table: &TranspositionTable) {
+    let book = match parse_opening_book() {
+        Ok(b) => b,
+        Err(_) => return,
+    };
 
-    for (zobrist, (mv, player)) in interim_book {
+    for (zobrist, (mv, player)) in book {
         let value: i64 = if player { 999 } else { -999 };
         let entry = TtEntry {
             mv: BitMove::new(mv),
             depth: 1,
             flag: EntryFlag::Exact,
             value,
+            age: tt_table.generation(),
         };
 
-        book.insert_no_refresh(zobrist, entry);
+        tt_table.insert(zobrist, entry);
     }
-    book.refresh();
+}
 
-    Ok(book)
+/// Creates a table of `size_mb` megabytes holding the opening book.
+pub fn tt_table_with_size(size_mb: usize) -> TranspositionTable {
+    let tt_table = TranspositionTable::new(size_mb);
+    add_opening_book(&tt_table);
+    tt_table
 }
 
 pub fn new_tt_table() -> TranspositionTable {
-    match parse_opening_book() {
-        Ok(b) => b,
-        Err(_) => TranspositionTable::new(),
-    }
+    tt_table_with_size(DEFAULT_HASH_SIZE)
 }
@@ -1,20 +1,43 @@
-use crate::search;
-use pleco::Board;
+use crate::search::{
+    dead_position, fifty_move_draw, threefold_repetition, Engine, InfoCallback, RootMove, Score,
+    SearchInfo, Timer, MAX_DEPTH,
+};
+use crate::utils::uci::parse_time_control;
+use pleco::{BitMove, Board};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use std::{io, thread, time};
 
-pub fn find_move_fen(fen: String, depth: u8, num_threads: u8) -> (String, i64) {
+/// Prints every search update as an UCI `info` line.
+pub fn print_info() -> InfoCallback {
+    Arc::new(|info: &SearchInfo| println!("{}", info))
+}
+
+pub fn find_move_fen(
+    fen: String,
+    depth: u8,
+    num_threads: u8,
+    multi_pv: u8,
+    hash: usize,
+) -> Vec<RootMove> {
     println!("HERE");
     match Board::from_fen(&fen) {
         Ok(board) => {
-            let (mv, score) =
-                search::search_parallel(board.clone(), depth, board.turn(), num_threads, 5);
-            (mv.stringify(), score)
+            let mut engine = Engine::new(num_threads, hash);
+            engine.set_multi_pv(multi_pv);
+            engine.search(&board, depth, Timer::new(5), print_info())
         }
-        Err(_) => ("".to_string(), 0),
+        Err(_) => vec![],
     }
 }
 
-pub fn find_move(moves: String, depth: u8, num_threads: u8) -> (String, i64) {
+pub fn find_move(
+    moves: String,
+    depth: u8,
+    num_threads: u8,
+    multi_pv: u8,
+    hash: usize,
+) -> Vec<RootMove> {
     let mut board = Board::start_pos();
 
     let mvs = moves.split(' ');
@@ -22,25 +45,29 @@ pub fn find_move(moves: String, depth: u8, num_threads: u8) -> (String, i64) {
         board.apply_uci_move(mv);
     }
 
-    let (mv, score) = search::search_parallel(board.clone(), depth, board.turn(), num_threads, 20);
-    (mv.stringify(), score)
+    let mut engine = Engine::new(num_threads, hash);
+    engine.set_multi_pv(multi_pv);
+    engine.search(&board, depth, Timer::new(20), print_info())
 }
 
 #[allow(dead_code)]
-pub fn from_start(depth: u8, n_threads: u8) {
+pub fn from_start(depth: u8, n_threads: u8, hash: usize) {
     use std::time::Instant;
 
     let mut board = Board::start_pos();
-    while !board.checkmate() && board.rule_50() != 50 {
+    let mut engine = Engine::new(n_threads, hash);
+    while !check_if_game_over(&board) {
         let mv_start = Instant::now();
-        let (mv, score) =
-            search::search_parallel(board.clone(), depth, board.turn(), n_threads, 20);
+        let best = engine
+            .search(&board, depth, Timer::new(20), print_info())
+            .swap_remove(0);
+        let (mv, score) = (best.mv, best.score);
         let end = mv_start.elapsed();
         board.apply_move(mv);
 
         println!(
             "SCORE: {}, MOVE: {}, player: {}, time: {:?}\n{}\n",
-            score,
+            Score::from_value(score),
             &mv,
             board.turn().other_player(),
             end,
@@ -73,11 +100,53 @@ pub fn get_move(wait: u64) -> String {
 }
 
 pub fn check_if_game_over(board: &Board) -> bool {
-    board.checkmate() || board.rule_50() == 50 || board.stalemate() || !board.is_ok_quick()
+    board.checkmate()
+        || board.stalemate()
+        || fifty_move_draw(board)
+        || threefold_repetition(board)
+        || dead_position(board)
+        || !board.is_ok_quick()
+}
+
+/// A search of the position after the expected reply, running until the real reply
+/// is known. The result is not used, the search fills the transposition table.
+struct Ponder {
+    handle: JoinHandle<()>,
+    timer: Timer,
+}
+
+fn start_pondering(board: &Board, reply: BitMove, engine: &Arc<Mutex<Engine>>) -> Ponder {
+    let mut board = board.parallel_clone();
+    board.apply_move(reply);
+    let engine = engine.clone();
+    let timer = Timer::infinite();
+    let search_timer = timer.clone();
+    let handle = thread::spawn(move || {
+        if !check_if_game_over(&board) {
+            let silent: InfoCallback = Arc::new(|_: &SearchInfo| {});
+            engine
+                .lock()
+                .unwrap()
+                .ponder(&board, MAX_DEPTH, search_timer, silent);
+        }
+    });
+    Ponder { handle, timer }
+}
+
+fn stop_pondering(ponder: &mut Option<Ponder>) {
+    if let Some(p) = ponder.take() {
+        p.timer.stop();
+        p.handle.join().unwrap();
+    }
 }
 
-pub fn keep_alive(moves: String, depth: u8, num_threads: u8) {
+/// Plays a game over stdin/stdout, one move per line. With `ponder` the engine keeps
+/// searching the expected reply while waiting for the opponent, after a ponder hit the
+/// search of the real position starts from the entries stored while pondering.
+pub fn keep_alive(moves: String, depth: u8, num_threads: u8, hash: usize, ponder: bool) {
     let mut board = Board::start_pos();
+    let engine = Arc::new(Mutex::new(Engine::new(num_threads, hash)));
+    let mut pondering: Option<Ponder> = None;
 
     let mvs = moves.split(' ');
     for mv in mvs {
@@ -85,23 +154,45 @@ pub fn keep_alive(moves: String, depth: u8, num_threads: u8) {
     }
 
     while !check_if_game_over(&board) {
-        let new_mv = get_move(100);
+        //A line is either "stop" or "<move|con> [wtime N btime N winc N binc N]"
+        let line = get_move(100);
+        stop_pondering(&mut pondering);
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let new_mv = tokens.first().copied().unwrap_or("");
         if new_mv == "stop" {
             break;
         }
 
         if !new_mv.is_empty() && new_mv != "con" {
-            let valid = board.apply_uci_move(&new_mv);
+            let valid = board.apply_uci_move(new_mv);
 
             if !valid {
                 break;
             }
         }
 
-        let (mv, score) =
-            search::search_parallel(board.clone(), depth, board.turn(), num_threads, 20);
-        println!("move{},{}", mv, score);
+        //With a clock the timer ends the search, the depth only limits untimed moves
+        let time_control = parse_time_control(&tokens);
+        let (search_depth, timer) = match time_control.wtime.or(time_control.btime) {
+            Some(_) => (
+                MAX_DEPTH,
+                Timer::from_time_control(&time_control, board.turn()),
+            ),
+            None => (depth, Timer::new(20)),
+        };
+
+        let best = engine
+            .lock()
+            .unwrap()
+            .search(&board, search_depth, timer, print_info())
+            .swap_remove(0);
+        let (mv, score) = (best.mv, best.score);
+        println!("move{},{}", mv, Score::from_value(score));
 
         board.apply_move(mv);
+        if let Some(reply) = best.pv.get(1).filter(|_| ponder) {
+            pondering = Some(start_pondering(&board, *reply, &engine));
+        }
     }
+    stop_pondering(&mut pondering);
 }
@@ -1,4 +1,16 @@
 mod book;
 pub mod game;
+pub mod uci;
+pub mod xboard;
 
 pub use book::*;
+
+/// The legal move `uci` of `board`, panics if there is none.
+#[cfg(test)]
+pub(crate) fn find_move(board: &pleco::Board, uci: &str) -> pleco::BitMove {
+    *board
+        .generate_moves()
+        .iter()
+        .find(|mv| mv.stringify() == uci)
+        .unwrap()
+}
@@ -0,0 +1,399 @@
+use crate::search::{Engine, RootMove, TimeControl, Timer, MAX_DEPTH};
+use crate::utils::game::print_info;
+use pleco::Board;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+const ENGINE_NAME: &str = "cyd";
+const ENGINE_AUTHOR: &str = "Jacob Andersson";
+const MAX_THREADS: u8 = 64;
+const MAX_MULTI_PV: u8 = 64;
+const MAX_HASH: usize = 65536;
+
+struct Options {
+    depth: u8,
+    engine: Arc<Mutex<Engine>>, //Locked by the search thread while it runs
+}
+
+impl Options {
+    fn new(depth: u8, num_threads: u8, hash: usize) -> Self {
+        Self {
+            depth,
+            engine: Arc::new(Mutex::new(Engine::new(num_threads, hash))),
+        }
+    }
+}
+
+pub fn parse_position(tokens: &[&str]) -> Option<Board> {
+    let moves_idx = tokens.iter().position(|t| *t == "moves");
+    let (setup, moves) = match moves_idx {
+        Some(idx) => (&tokens[..idx], &tokens[idx + 1..]),
+        None => (tokens, &tokens[tokens.len()..]),
+    };
+
+    let mut board = match setup.first() {
+        Some(&"startpos") => Board::start_pos(),
+        Some(&"fen") => Board::from_fen(&setup[1..].join(" ")).ok()?,
+        _ => return None,
+    };
+
+    for mv in moves {
+        if !board.apply_uci_move(mv) {
+            return None;
+        }
+    }
+    Some(board)
+}
+
+fn parse_value<T: std::str::FromStr>(tokens: &[&str], name: &str) -> Option<T> {
+    tokens
+        .iter()
+        .position(|t| *t == name)
+        .and_then(|idx| tokens.get(idx + 1))
+        .and_then(|v| v.parse().ok())
+}
+
+/// Reads the clock fields of a `go` command, missing fields are left unset.
+pub fn parse_time_control(tokens: &[&str]) -> TimeControl {
+    TimeControl {
+        wtime: parse_value(tokens, "wtime"),
+        btime: parse_value(tokens, "btime"),
+        winc: parse_value(tokens, "winc").unwrap_or(0),
+        binc: parse_value(tokens, "binc").unwrap_or(0),
+        movestogo: parse_value(tokens, "movestogo"),
+        movetime: parse_value(tokens, "movetime"),
+        nodes: parse_value(tokens, "nodes"),
+    }
+}
+
+/// Returns the depth and the time control for a `go` command. The depth, the node limit
+/// and the clock combine, the search stops at the first one reached. Infinite analysis
+/// ignores the clock. Only a bare `go` searches to `default_depth`, any other limit
+/// searches up to `MAX_DEPTH`.
+fn parse_go(tokens: &[&str], default_depth: u8) -> (u8, TimeControl) {
+    let infinite = tokens.contains(&"infinite");
+    let time_control = if infinite {
+        TimeControl::default()
+    } else {
+        parse_time_control(tokens)
+    };
+    let limited = time_control.wtime.is_some()
+        || time_control.btime.is_some()
+        || time_control.movetime.is_some()
+        || time_control.nodes.is_some();
+    match parse_value::<u8>(tokens, "depth") {
+        Some(depth) => (depth.clamp(1, MAX_DEPTH), time_control),
+        None if infinite || limited => (MAX_DEPTH, time_control),
+        None => (default_depth, time_control),
+    }
+}
+
+fn set_option(tokens: &[&str], engine: &mut Engine) {
+    let name_idx = match tokens.iter().position(|t| *t == "name") {
+        Some(idx) => idx + 1,
+        None => return,
+    };
+    let value_idx = tokens
+        .iter()
+        .position(|t| *t == "value")
+        .unwrap_or(tokens.len());
+    let name = tokens[name_idx..value_idx].join(" ");
+    let value = tokens.get(value_idx + 1..).unwrap_or(&[]).join(" ");
+
+    if name.eq_ignore_ascii_case("threads") {
+        if let Ok(n) = value.parse::<u8>() {
+            engine.set_num_threads(n.clamp(1, MAX_THREADS));
+        }
+    } else if name.eq_ignore_ascii_case("multipv") {
+        if let Ok(n) = value.parse::<u8>() {
+            engine.set_multi_pv(n.clamp(1, MAX_MULTI_PV));
+        }
+    } else if name.eq_ignore_ascii_case("hash") {
+        if let Ok(mb) = value.parse::<usize>() {
+            engine.set_hash(mb.clamp(1, MAX_HASH));
+        }
+    } else if name.eq_ignore_ascii_case("clear hash") {
+        engine.clear_hash();
+    }
+}
+
+struct Search {
+    handle: JoinHandle<()>,
+    timer: Timer,
+    release: Sender<()>, //Releases the best move of an infinite or pondering search
+}
+
+/// The `bestmove` command, with the expected reply to ponder on if the line has one.
+fn best_move(line: &RootMove) -> String {
+    match line.pv.get(1) {
+        Some(reply) => format!("bestmove {} ponder {}", line.mv, reply),
+        None => format!("bestmove {}", line.mv),
+    }
+}
+
+/// Starts searching in the background. The best move of an infinite search is only
+/// printed once the GUI sends `stop`, the one of a pondering search once the GUI sends
+/// `stop` or `ponderhit`, even if the search finished before.
+fn go(
+    board: Board,
+    depth: u8,
+    options: &Options,
+    time_control: TimeControl,
+    infinite: bool,
+    ponder: bool,
+) -> Search {
+    let engine = options.engine.clone();
+    let mut timer = Timer::from_time_control(&time_control, board.turn());
+    if ponder {
+        timer = timer.ponder();
+    }
+    let search_timer = timer.clone();
+    let (release, released) = mpsc::channel();
+    let handle = thread::spawn(move || {
+        let lines =
+            engine
+                .lock()
+                .unwrap()
+                .search(&board, depth, search_timer.clone(), print_info());
+        if infinite || search_timer.pondering() {
+            released.recv().ok();
+        }
+        println!("{}", best_move(&lines[0]));
+    });
+    Search {
+        handle,
+        timer,
+        release,
+    }
+}
+
+/// The opponent played the move the engine is pondering on, the search goes on as a
+/// normal one with the time limits of the `go ponder` command.
+fn ponder_hit(search: &Option<Search>) {
+    if let Some(s) = search {
+        s.timer.ponder_hit();
+        s.release.send(()).ok();
+    }
+}
+
+/// Stops the running search, if any, and waits for it to print its best move.
+fn stop_search(search: &mut Option<Search>) {
+    if let Some(s) = search.take() {
+        s.release.send(()).ok();
+        s.timer.stop();
+        s.handle.join().unwrap();
+    }
+}
+
+pub fn uci_loop(depth: u8, num_threads: u8, hash: usize) {
+    let options = Options::new(depth, num_threads, hash);
+    let mut board = Board::start_pos();
+    let mut search: Option<Search> = None;
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        match tokens.first() {
+            Some(&"uci") => {
+                println!("id name {}", ENGINE_NAME);
+                println!("id author {}", ENGINE_AUTHOR);
+                println!(
+                    "option name Threads type spin default {} min 1 max {}",
+                    num_threads, MAX_THREADS
+                );
+                println!(
+                    "option name MultiPV type spin default 1 min 1 max {}",
+                    MAX_MULTI_PV
+                );
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    hash, MAX_HASH
+                );
+                println!("option name Clear Hash type button");
+                //Only tells that the GUI may send `go ponder`, there is nothing to set
+                println!("option name Ponder type check default false");
+                println!("uciok");
+            }
+            Some(&"isready") => println!("readyok"),
+            Some(&"ucinewgame") => {
+                stop_search(&mut search);
+                board = Board::start_pos();
+                options.engine.lock().unwrap().new_game();
+            }
+            Some(&"setoption") => {
+                stop_search(&mut search);
+                set_option(&tokens, &mut options.engine.lock().unwrap());
+            }
+            Some(&"position") => {
+                stop_search(&mut search);
+                if let Some(b) = parse_position(&tokens[1..]) {
+                    board = b;
+                }
+            }
+            Some(&"go") => {
+                stop_search(&mut search);
+                let (d, time_control) = parse_go(&tokens[1..], options.depth);
+                let infinite = tokens.contains(&"infinite");
+                let ponder = tokens.contains(&"ponder");
+                search = Some(go(
+                    board.parallel_clone(),
+                    d,
+                    &options,
+                    time_control,
+                    infinite,
+                    ponder,
+                ));
+            }
+            Some(&"ponderhit") => ponder_hit(&search),
+            Some(&"stop") => stop_search(&mut search),
+            Some(&"quit") => break,
+            _ => {}
+        }
+    }
+    stop_search(&mut search);
+}
+
+#[cfg(test)]
+mod uci_test {
+    use super::*;
+    use crate::search::transposition_table::{EntryFlag, TranspositionTable, TtEntry};
+    use crate::utils::find_move;
+    use pleco::{BitMove, Player};
+
+    #[test]
+    fn position_startpos_with_moves() {
+        let tokens: Vec<&str> = "startpos moves e2e4 e7e5".split(' ').collect();
+        let board = parse_position(&tokens).unwrap();
+        assert_eq!(board.moves_played(), 2);
+        assert_eq!(board.turn(), Player::White);
+    }
+
+    #[test]
+    fn position_fen() {
+        let fen = "2k5/8/4q3/8/2B5/8/8/1K6 w - - 0 1";
+        let command = format!("fen {}", fen);
+        let tokens: Vec<&str> = command.split(' ').collect();
+        let board = parse_position(&tokens).unwrap();
+        assert_eq!(board.fen(), fen);
+    }
+
+    #[test]
+    fn position_illegal_move() {
+        let tokens: Vec<&str> = "startpos moves e2e5".split(' ').collect();
+        assert!(parse_position(&tokens).is_none());
+    }
+
+    #[test]
+    fn set_options() {
+        let mut engine = Engine::new(1, 1);
+        let set = |engine: &mut Engine, command: &str| {
+            let tokens: Vec<&str> = command.split(' ').collect();
+            set_option(&tokens, engine);
+        };
+
+        set(&mut engine, "setoption name Threads value 4");
+        set(&mut engine, "setoption name MultiPV value 3");
+        set(&mut engine, "setoption name Hash value 2");
+        assert_eq!(engine.num_threads(), 4);
+        assert_eq!(engine.multi_pv(), 3);
+        assert_eq!(engine.hash(), 2);
+        assert!(engine.tt_table().capacity() > TranspositionTable::new(1).capacity());
+
+        let entry = TtEntry {
+            mv: BitMove::null(),
+            depth: 1,
+            flag: EntryFlag::Exact,
+            value: 0,
+            age: engine.tt_table().generation(),
+        };
+        engine.tt_table().insert(1, entry);
+        set(&mut engine, "setoption name Clear Hash");
+        assert_eq!(engine.tt_table().get(&1), None);
+    }
+
+    #[test]
+    fn go_with_clock() {
+        let tokens: Vec<&str> = "wtime 60000 btime 30000 winc 1000 movestogo 20"
+            .split(' ')
+            .collect();
+        let (depth, tc) = parse_go(&tokens, 5);
+        assert_eq!(depth, MAX_DEPTH);
+        assert_eq!(tc.wtime, Some(60000));
+        assert_eq!(tc.btime, Some(30000));
+        assert_eq!(tc.winc, 1000);
+        assert_eq!(tc.binc, 0);
+        assert_eq!(tc.movestogo, Some(20));
+        assert_eq!(tc.movetime, None);
+    }
+
+    #[test]
+    fn go_depth_keeps_the_clock() {
+        let tokens: Vec<&str> = "depth 8 wtime 1000".split(' ').collect();
+        let (depth, tc) = parse_go(&tokens, 5);
+        assert_eq!(depth, 8);
+        assert_eq!(tc.wtime, Some(1000));
+    }
+
+    #[test]
+    fn go_nodes_and_infinite() {
+        let tokens: Vec<&str> = "nodes 50000 wtime 1000".split(' ').collect();
+        let (depth, tc) = parse_go(&tokens, 5);
+        assert_eq!(depth, MAX_DEPTH);
+        assert_eq!(tc.nodes, Some(50000));
+        assert_eq!(tc.wtime, Some(1000));
+
+        let tokens: Vec<&str> = "depth 3 nodes 100".split(' ').collect();
+        let (depth, tc) = parse_go(&tokens, 5);
+        assert_eq!(depth, 3);
+        assert_eq!(tc.nodes, Some(100));
+
+        let tokens: Vec<&str> = vec!["infinite"];
+        assert_eq!(parse_go(&tokens, 5), (MAX_DEPTH, TimeControl::default()));
+    }
+
+    #[test]
+    fn best_move_with_ponder_move() {
+        let mut board = Board::start_pos();
+        let moves: Vec<BitMove> = ["e2e4", "e7e5"]
+            .iter()
+            .map(|uci| {
+                let mv = find_move(&board, uci);
+                board.apply_move(mv);
+                mv
+            })
+            .collect();
+
+        let line = RootMove::new(moves[0], 20, moves.clone());
+        assert_eq!(best_move(&line), "bestmove e2e4 ponder e7e5");
+        let line = RootMove::new(moves[0], 20, vec![moves[0]]);
+        assert_eq!(best_move(&line), "bestmove e2e4");
+    }
+
+    #[test]
+    fn go_ponder_keeps_the_clock() {
+        let tokens: Vec<&str> = "ponder wtime 60000 btime 60000".split(' ').collect();
+        let (depth, tc) = parse_go(&tokens, 5);
+        assert_eq!(depth, MAX_DEPTH);
+        assert_eq!(tc.wtime, Some(60000));
+    }
+
+    #[test]
+    fn go_movetime() {
+        let tokens: Vec<&str> = "movetime 500".split(' ').collect();
+        let (depth, tc) = parse_go(&tokens, 5);
+        assert_eq!(depth, MAX_DEPTH);
+        assert_eq!(tc, TimeControl::move_time(500));
+    }
+
+    #[test]
+    fn bare_go_uses_default_depth() {
+        assert_eq!(parse_go(&[], 5), (5, TimeControl::default()));
+    }
+}
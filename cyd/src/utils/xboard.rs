@@ -0,0 +1,281 @@
+use crate::search::{Engine, Score, SearchInfo, TimeControl, Timer, MAX_DEPTH};
+use crate::utils::game::check_if_game_over;
+use pleco::{Board, Player};
+use std::io::{self, BufRead};
+use std::sync::Arc;
+
+const ENGINE_NAME: &str = "cyd";
+const XBOARD_MATE: i64 = 100_000; //Mate in N moves is shown as 100000 + N
+
+struct State {
+    board: Board,
+    engine_color: Option<Player>,
+    depth: Option<u8>, //Set by sd, limits the search even with a clock
+    default_depth: u8, //Used without sd and without a clock
+    engine: Engine,
+    moves_per_session: u64,
+    increment: u64,     //seconds
+    time_per_move: u64, //seconds, 0 when not set by st
+    time_left: u64,     //centiseconds
+    post: bool,
+}
+
+impl State {
+    fn new(depth: u8, num_threads: u8, hash: usize) -> Self {
+        Self {
+            board: Board::start_pos(),
+            engine_color: None,
+            depth: None,
+            default_depth: depth,
+            engine: Engine::new(num_threads, hash),
+            moves_per_session: 0,
+            increment: 0,
+            time_per_move: 0,
+            time_left: 0,
+            post: false,
+        }
+    }
+
+    fn time_control(&self) -> TimeControl {
+        if self.time_per_move > 0 {
+            return TimeControl::move_time(self.time_per_move * 1000);
+        }
+        if self.time_left == 0 {
+            return TimeControl::default();
+        }
+
+        let movestogo = if self.moves_per_session > 0 {
+            let played = (self.board.moves_played() as u64 / 2) % self.moves_per_session;
+            Some(self.moves_per_session - played)
+        } else {
+            None
+        };
+        let time_left = Some(self.time_left * 10);
+        let inc = self.increment * 1000;
+        TimeControl {
+            wtime: time_left,
+            btime: time_left,
+            winc: inc,
+            binc: inc,
+            movestogo,
+            movetime: None,
+            nodes: None,
+        }
+    }
+
+    /// The `sd` limit if one was set, otherwise the timer ends a timed search and only
+    /// untimed searches stop at the default depth.
+    fn search_depth(&self) -> u8 {
+        match self.depth {
+            Some(depth) => depth,
+            None if self.time_per_move > 0 || self.time_left > 0 => MAX_DEPTH,
+            None => self.default_depth,
+        }
+    }
+
+    fn think(&mut self) {
+        if check_if_game_over(&self.board) {
+            return;
+        }
+
+        let timer = Timer::from_time_control(&self.time_control(), self.board.turn());
+        let post = self.post;
+        let lines = self.engine.search(
+            &self.board,
+            self.search_depth(),
+            timer,
+            Arc::new(move |info: &SearchInfo| {
+                if post {
+                    println!("{}", thinking_output(info));
+                }
+            }),
+        );
+        let mv = lines[0].mv;
+        self.board.apply_move(mv);
+        println!("move {}", mv);
+    }
+
+    fn engine_to_move(&self) -> bool {
+        self.engine_color == Some(self.board.turn())
+    }
+}
+
+/// Score in centipawns, mates use the xboard convention of +-(100000 + moves).
+fn xboard_score(score: i64) -> i64 {
+    match Score::from_value(score) {
+        Score::Cp(cp) => cp,
+        Score::Mate(moves) if moves > 0 => XBOARD_MATE + moves,
+        Score::Mate(moves) => -XBOARD_MATE + moves,
+    }
+}
+
+/// Formats a search update as "ply score time nodes pv", time in centiseconds.
+fn thinking_output(info: &SearchInfo) -> String {
+    format!(
+        "{} {} {} {} {}",
+        info.depth,
+        xboard_score(info.score),
+        info.time / 10,
+        info.nodes,
+        info.pv_string()
+    )
+}
+
+/// Depth limit of an `sd` command, kept within the depths the search supports.
+fn parse_depth(token: Option<&&str>, default: u8) -> u8 {
+    token
+        .and_then(|d| d.parse::<u8>().ok())
+        .unwrap_or(default)
+        .clamp(1, MAX_DEPTH)
+}
+
+/// Parses the base time of a `level` command, given either as minutes or minutes:seconds.
+fn parse_base_time(base: &str) -> Option<u64> {
+    let mut parts = base.split(':');
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: u64 = match parts.next() {
+        Some(s) => s.parse().ok()?,
+        None => 0,
+    };
+    Some(minutes * 60 + seconds)
+}
+
+fn undo(board: &mut Board, plies: u8) {
+    for _ in 0..plies {
+        if board.last_move().is_none() {
+            break;
+        }
+        board.undo_move();
+    }
+}
+
+pub fn xboard_loop(depth: u8, num_threads: u8, hash: usize) {
+    let mut state = State::new(depth, num_threads, hash);
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        match tokens.first() {
+            Some(&"protover") => {
+                println!(
+                    "feature myname=\"{}\" usermove=1 setboard=1 ping=1 memory=1 sigint=0 sigterm=0 done=1",
+                    ENGINE_NAME
+                );
+            }
+            Some(&"ping") => println!("pong {}", tokens.get(1).unwrap_or(&"")),
+            Some(&"new") => {
+                state.board = Board::start_pos();
+                state.engine_color = Some(Player::Black);
+                state.depth = None;
+                state.time_per_move = 0;
+                state.engine.new_game();
+            }
+            Some(&"setboard") => match Board::from_fen(&tokens[1..].join(" ")) {
+                Ok(b) => state.board = b,
+                Err(_) => println!("tellusererror Illegal position"),
+            },
+            Some(&"force") => state.engine_color = None,
+            Some(&"go") => {
+                state.engine_color = Some(state.board.turn());
+                state.think();
+            }
+            Some(&"usermove") => {
+                let mv = tokens.get(1).unwrap_or(&"");
+                if !state.board.apply_uci_move(mv) {
+                    println!("Illegal move: {}", mv);
+                } else if state.engine_to_move() {
+                    state.think();
+                }
+            }
+            Some(&"level") if tokens.len() >= 4 => {
+                state.moves_per_session = tokens[1].parse().unwrap_or(0);
+                state.increment = tokens[3].parse().unwrap_or(0);
+                state.time_per_move = 0;
+                if let Some(base) = parse_base_time(tokens[2]) {
+                    state.time_left = base * 100;
+                }
+            }
+            Some(&"st") => {
+                state.time_per_move = tokens.get(1).and_then(|t| t.parse().ok()).unwrap_or(0)
+            }
+            Some(&"sd") => state.depth = Some(parse_depth(tokens.get(1), state.default_depth)),
+            Some(&"time") => {
+                state.time_left = tokens.get(1).and_then(|t| t.parse().ok()).unwrap_or(0)
+            }
+            Some(&"undo") => undo(&mut state.board, 1),
+            Some(&"remove") => undo(&mut state.board, 2),
+            Some(&"result") => state.engine_color = None,
+            Some(&"memory") => {
+                if let Some(mb) = tokens.get(1).and_then(|m| m.parse::<usize>().ok()) {
+                    state.engine.set_hash(mb);
+                }
+            }
+            Some(&"post") => state.post = true,
+            Some(&"nopost") => state.post = false,
+            Some(&"quit") => break,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod xboard_test {
+    use super::*;
+
+    #[test]
+    fn base_time_minutes() {
+        assert_eq!(parse_base_time("5"), Some(300));
+    }
+
+    #[test]
+    fn base_time_minutes_seconds() {
+        assert_eq!(parse_base_time("0:30"), Some(30));
+        assert_eq!(parse_base_time("2:15"), Some(135));
+    }
+
+    #[test]
+    fn depth_is_clamped() {
+        assert_eq!(parse_depth(Some(&"7"), 5), 7);
+        assert_eq!(parse_depth(Some(&"255"), 5), MAX_DEPTH);
+        assert_eq!(parse_depth(Some(&"0"), 5), 1);
+        assert_eq!(parse_depth(None, 5), 5);
+    }
+
+    #[test]
+    fn depth_limit_only_from_sd() {
+        let mut state = State::new(5, 1, 1);
+        assert_eq!(state.search_depth(), 5);
+        state.time_left = 30_000;
+        assert_eq!(state.search_depth(), MAX_DEPTH);
+        state.depth = Some(parse_depth(Some(&"8"), state.default_depth));
+        assert_eq!(state.search_depth(), 8);
+    }
+
+    #[test]
+    fn mate_scores() {
+        use crate::search::{mate_in, mated_in};
+        assert_eq!(xboard_score(42), 42);
+        assert_eq!(xboard_score(mate_in(3)), 100_002);
+        assert_eq!(xboard_score(mated_in(2)), -100_001);
+    }
+
+    #[test]
+    fn time_control_from_clock() {
+        let mut state = State::new(5, 1, 1);
+        state.time_left = 6000;
+        state.moves_per_session = 40;
+        state.increment = 2;
+        let tc = state.time_control();
+        assert_eq!(tc.wtime, Some(60_000));
+        assert_eq!(tc.binc, 2_000);
+        assert_eq!(tc.movestogo, Some(40));
+
+        state.time_per_move = 7;
+        assert_eq!(state.time_control(), TimeControl::move_time(7_000));
+    }
+}
@@ -5,6 +5,6 @@ const { str, num } = envalid;
 module.exports = envalid.cleanEnv(process.env, {
   DEBUG: num({ default: 0 }),
   TOKEN: str({}),
-  DEPTH: num({ default: 5 }),
+  DEPTH: num({ default: 6 }),
   THREADS: num({ default: 1 }),
 });
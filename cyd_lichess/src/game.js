@@ -55,7 +55,7 @@ class boardStateStream extends Writable {
         this.game = new cyd(moves, DEPTH, THREADS);
       }
 
-      this.game.makeMove(moves);
+      this.game.makeMove(moves, gameState || data);
       const { move, score } = await this.game.getMove();
       console.log(`MOVE: ${move}, SCORE: ${score}`);
       postMove(GAME_ID, move);
@@ -98,6 +98,7 @@ class cyd {
       '--num-threads',
       threads,
       '--alive',
+      '--ponder',
     ]);
 
     this.process.stdout.on('error', (error) => {
@@ -108,29 +109,32 @@ class cyd {
       console.log('Search closed with', code);
     });
 
+    this.buffer = '';
     this.process.stdout.on('data', (data) => {
-      if (data.includes('move')) {
-        let split = data
-          .toString()
-          .replace('move', '')
-          .trim()
-          .toString()
-          .split(',');
-        let move = split[0];
-        let score = split[1];
-
-        this.lastOutput = { move, score };
+      this.buffer += data.toString();
+      const lines = this.buffer.split('\n');
+      this.buffer = lines.pop();
+
+      for (const line of lines) {
+        if (line.startsWith('info')) {
+          if (DEBUG) {
+            console.log(line);
+          }
+        } else if (line.startsWith('move')) {
+          const [move, score] = line.replace('move', '').trim().split(',');
+          this.lastOutput = { move, score };
+        }
       }
     });
   }
 
-  makeMove(moves) {
-    if (moves) {
-      let lastMove = moves.split(' ').pop() || 'con';
-      this.process.stdin.write(lastMove + '\n');
-    } else {
-      this.process.stdin.write('con\n'); 
+  makeMove(moves, clock) {
+    let lastMove = (moves && moves.split(' ').pop()) || 'con';
+    if (clock && clock.wtime !== undefined) {
+      const { wtime, btime, winc, binc } = clock;
+      lastMove += ` wtime ${wtime} btime ${btime} winc ${winc} binc ${binc}`;
     }
+    this.process.stdin.write(lastMove + '\n');
   }
 
   async sleep(msec) {
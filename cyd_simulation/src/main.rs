@@ -1,8 +1,9 @@
 use std::thread;
 use std::time::Instant;
 
-use cyd::evaluate::EvalParameters;
-use cyd::search::alpha_beta;
+use cyd::evaluate::{EvalParameters, Tapered};
+use cyd::search::{iterative_deepening, SearchInfo, SearchParameters, ThreadData, Timer, INFINITY};
+use cyd::utils::game::check_if_game_over;
 use cyd::utils::new_tt_table;
 
 use pleco::{Board, Player};
@@ -11,22 +12,29 @@ use rand::thread_rng;
 use rand_distr::{Distribution, Normal};
 
 const DEPTH: u8 = 5;
+const MOVE_TIME: u64 = 60; //seconds
 
 struct ParameterRands {
     psq: Normal<f32>,
     pinned: Normal<f32>,
     king_safety: Normal<f32>,
+    pawns: Normal<f32>,
+    pieces: Normal<f32>,
+    margin: Normal<f32>,
+    lmr: Normal<f32>,
 }
 
-fn play_game(
-    white_params: EvalParameters,
-    black_params: EvalParameters,
-    mv: &str,
-) -> Option<Player> {
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Parameters {
+    eval: EvalParameters,
+    search: SearchParameters,
+}
+
+fn play_game(white_params: Parameters, black_params: Parameters, mv: &str) -> Option<Player> {
     let mut board = Board::start_pos();
     board.apply_uci_move(mv);
 
-    while !board.checkmate() && board.rule_50() != 50 {
+    while !check_if_game_over(&board) {
         let mut tt_table = new_tt_table();
         let player = board.turn();
         let params = match player {
@@ -34,20 +42,24 @@ fn play_game(
             Player::Black => black_params,
         };
 
-        let (mv, _) = alpha_beta(
+        let lines = iterative_deepening(
             board.clone(),
             DEPTH,
             player,
-            -9999.,
-            9999.,
+            -INFINITY,
+            INFINITY,
             &mut tt_table,
             true,
-            &Some(params),
+            &Some(params.eval),
+            &Timer::new(MOVE_TIME),
+            &mut ThreadData::with_params(params.search),
+            1,
+            &|_: &SearchInfo| {},
         );
-        if mv.to_string() == "a1a1" {
+        if lines[0].mv.is_null() {
             break;
         }
-        board.apply_move(mv);
+        board.apply_move(lines[0].mv);
     }
 
     if board.checkmate() {
@@ -57,7 +69,7 @@ fn play_game(
     }
 }
 
-fn battle(param1: EvalParameters, param2: EvalParameters) -> EvalParameters {
+fn battle(param1: Parameters, param2: Parameters) -> Parameters {
     let mut param1_wins = 0;
     let mut param2_wins = 0;
 
@@ -105,18 +117,51 @@ fn gen_new(param: f32, dist: Normal<f32>, temp: f32) -> f32 {
     0_f32.max(param + dist.sample(&mut rng) * temp)
 }
 
-fn gen_new_parameters(
-    base: &EvalParameters,
-    rands: &ParameterRands,
-    i: f32,
-    max_i: f32,
-) -> EvalParameters {
-    let temp = 1. - i / max_i;
+fn gen_new_tapered(weight: Tapered, dist: Normal<f32>, temp: f32) -> Tapered {
+    Tapered::new(
+        gen_new(weight.mg, dist, temp),
+        gen_new(weight.eg, dist, temp),
+    )
+}
 
-    EvalParameters {
-        psq: gen_new(base.psq, rands.psq, temp),
-        pinned: gen_new(base.pinned, rands.pinned, temp),
-        king_safety: gen_new(base.king_safety, rands.king_safety, temp),
+fn gen_new_margin(margin: i64, dist: Normal<f32>, temp: f32) -> i64 {
+    gen_new(margin as f32, dist, temp).round() as i64
+}
+
+fn gen_new_parameters(base: &Parameters, rands: &ParameterRands, i: f32, max_i: f32) -> Parameters {
+    let temp = 1. - i / max_i;
+    let (eval, search) = (&base.eval, &base.search);
+
+    Parameters {
+        eval: EvalParameters {
+            psq: gen_new_tapered(eval.psq, rands.psq, temp),
+            pinned: gen_new_tapered(eval.pinned, rands.pinned, temp),
+            king_safety: gen_new_tapered(eval.king_safety, rands.king_safety, temp),
+            doubled_pawn: gen_new_tapered(eval.doubled_pawn, rands.pawns, temp),
+            isolated_pawn: gen_new_tapered(eval.isolated_pawn, rands.pawns, temp),
+            backward_pawn: gen_new_tapered(eval.backward_pawn, rands.pawns, temp),
+            connected_pawn: gen_new_tapered(eval.connected_pawn, rands.pawns, temp),
+            passed_pawn: gen_new_tapered(eval.passed_pawn, rands.pawns, temp),
+            blocked_passed_pawn: gen_new_tapered(eval.blocked_passed_pawn, rands.pawns, temp),
+            knight_mobility: gen_new_tapered(eval.knight_mobility, rands.pieces, temp),
+            bishop_mobility: gen_new_tapered(eval.bishop_mobility, rands.pieces, temp),
+            rook_mobility: gen_new_tapered(eval.rook_mobility, rands.pieces, temp),
+            queen_mobility: gen_new_tapered(eval.queen_mobility, rands.pieces, temp),
+            bishop_pair: gen_new_tapered(eval.bishop_pair, rands.pieces, temp),
+            rook_open_file: gen_new_tapered(eval.rook_open_file, rands.pieces, temp),
+            rook_semi_open_file: gen_new_tapered(eval.rook_semi_open_file, rands.pieces, temp),
+            rook_on_seventh: gen_new_tapered(eval.rook_on_seventh, rands.pieces, temp),
+            knight_outpost: gen_new_tapered(eval.knight_outpost, rands.pieces, temp),
+            trapped_piece: gen_new_tapered(eval.trapped_piece, rands.pieces, temp),
+        },
+        search: SearchParameters {
+            lmr_base: gen_new(search.lmr_base, rands.lmr, temp),
+            lmr_divisor: gen_new(search.lmr_divisor, rands.lmr, temp).max(0.5),
+            rfp_margin: gen_new_margin(search.rfp_margin, rands.margin, temp),
+            futility_margin: gen_new_margin(search.futility_margin, rands.margin, temp),
+            razor_margin: gen_new_margin(search.razor_margin, rands.margin, temp),
+            ..*search
+        },
     }
 }
 
@@ -124,13 +169,16 @@ fn main() {
     let rands = ParameterRands {
         psq: Normal::new(5., 5.).unwrap(),
         pinned: Normal::new(5., 5.).unwrap(),
-        king_safety: Normal::new(5., 5.).unwrap(),
+        king_safety: Normal::new(0., 0.1).unwrap(),
+        pawns: Normal::new(0., 5.).unwrap(),
+        pieces: Normal::new(0., 3.).unwrap(),
+        margin: Normal::new(0., 30.).unwrap(),
+        lmr: Normal::new(0., 0.25).unwrap(),
     };
 
-    let mut best = EvalParameters {
-        psq: 0.5,
-        pinned: 10.,
-        king_safety: 2.,
+    let mut best = Parameters {
+        eval: EvalParameters::default(),
+        search: SearchParameters::default(),
     };
 
     let mut same_counter = 1;